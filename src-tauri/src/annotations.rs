@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+use std::path::Path;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::{AppHandle, Runtime};
+
+use crate::bookmark_manager::{read_bookmarks_from_workspace, ParsedBookmark};
+use crate::db::open_db;
+use crate::journal::{record_operation, snapshot_rows, table_rows, Operation, RowSnapshot};
+
+/// 可用的颜色标签
+const COLOR_LABELS: [&str; 7] = ["red", "orange", "yellow", "green", "blue", "purple", "gray"];
+
+/// IDEA 书签的身份标识：项目名 + 文件路径 + 行号
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BookmarkKey {
+    pub project_name: String,
+    pub file_path: String,
+    pub line_number: i32,
+}
+
+/// 附加在书签上的本地信息
+#[derive(serde::Serialize, Clone, Debug, Default)]
+pub struct Annotation {
+    /// Markdown 格式的备注
+    pub note: String,
+    pub color: Option<String>,
+    pub pinned: bool,
+    pub favorite: bool,
+    pub tags: Vec<String>,
+    pub updated_at: Option<String>,
+}
+
+/// 带本地信息的书签
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct AnnotatedBookmark {
+    #[serde(flatten)]
+    pub bookmark: ParsedBookmark,
+    pub annotation: Annotation,
+}
+
+/// 标签及其使用次数
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct TagInfo {
+    pub id: i64,
+    pub name: String,
+    pub usage_count: i64,
+}
+
+/// 按本地信息筛选书签，所有条件均可省略
+#[derive(serde::Deserialize, Default, Debug)]
+pub struct AnnotationFilter {
+    /// 必须同时包含这些标签
+    pub tags: Option<Vec<String>>,
+    pub color: Option<String>,
+    pub pinned: Option<bool>,
+    pub favorite: Option<bool>,
+    pub has_note: Option<bool>,
+}
+
+impl BookmarkKey {
+    fn of(bookmark: &ParsedBookmark) -> Self {
+        BookmarkKey {
+            project_name: bookmark.project_name.clone(),
+            file_path: bookmark.file_path.clone(),
+            line_number: bookmark.line_number,
+        }
+    }
+}
+
+impl AnnotationFilter {
+    fn matches(&self, a: &Annotation) -> bool {
+        if let Some(tags) = &self.tags {
+            if !tags.iter().all(|t| a.tags.contains(t)) {
+                return false;
+            }
+        }
+        if let Some(color) = &self.color {
+            if a.color.as_ref() != Some(color) {
+                return false;
+            }
+        }
+        if self.pinned.is_some_and(|p| p != a.pinned) || self.favorite.is_some_and(|f| f != a.favorite) {
+            return false;
+        }
+        if self.has_note.is_some_and(|h| h == a.note.trim().is_empty()) {
+            return false;
+        }
+        true
+    }
+}
+
+/// 获取书签对应的本地信息记录 ID，不存在时创建
+fn ensure_annotation(conn: &Connection, key: &BookmarkKey) -> Result<i64, String> {
+    conn.execute(
+        "INSERT OR IGNORE INTO bookmark_annotations (project_name, file_path, line_number, updated_at) VALUES (?1, ?2, ?3, ?4)",
+        params![key.project_name, key.file_path, key.line_number, Utc::now().to_rfc3339()],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.query_row(
+        "SELECT id FROM bookmark_annotations WHERE project_name = ?1 AND file_path = ?2 AND line_number = ?3",
+        params![key.project_name, key.file_path, key.line_number],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// 书签本地信息、相关标签及标签关联的快照，顺序为父表在前
+fn snapshot_annotation(conn: &Connection, key: &BookmarkKey, tag: Option<&str>) -> Result<[Vec<RowSnapshot>; 3], String> {
+    let annotation = snapshot_rows(
+        conn,
+        "bookmark_annotations",
+        "project_name = ?1 AND file_path = ?2 AND line_number = ?3",
+        &[&key.project_name, &key.file_path, &key.line_number],
+    )?;
+    let tags = match tag {
+        Some(tag) => snapshot_rows(conn, "tags", "name = ?1", &[&tag])?,
+        None => Vec::new(),
+    };
+    let links = snapshot_rows(
+        conn,
+        "bookmark_tags",
+        "annotation_id IN (SELECT id FROM bookmark_annotations WHERE project_name = ?1 AND file_path = ?2 AND line_number = ?3)",
+        &[&key.project_name, &key.file_path, &key.line_number],
+    )?;
+    Ok([annotation, tags, links])
+}
+
+/// 在事务中修改书签的本地信息，并把修改前后的快照记录到操作历史
+/// tag 为涉及的标签名，新建标签时一并记录，撤销时删除
+fn change_annotation<R: Runtime>(
+    app: &AppHandle<R>,
+    key: &BookmarkKey,
+    tag: Option<&str>,
+    summary: &str,
+    change: impl FnOnce(&Connection) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut conn = open_db(app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let before = snapshot_annotation(&tx, key, tag)?;
+    change(&tx)?;
+    let after = snapshot_annotation(&tx, key, tag)?;
+
+    let tables = [("bookmark_annotations", &["id"][..]), ("tags", &["id"][..]), ("bookmark_tags", &["annotation_id", "tag_id"][..])];
+    let operations: Vec<Operation> = tables
+        .iter()
+        .zip(before.into_iter().zip(after))
+        .filter_map(|((table, key), (before, after))| table_rows(table, key, before, after))
+        .collect();
+    if !operations.is_empty() {
+        let file_name = Path::new(&key.file_path).file_name().unwrap_or_default().to_string_lossy();
+        record_operation(&tx, &format!("{}：{}:{}", summary, file_name, key.line_number), &Operation::Batch { operations })?;
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// 更新书签本地信息的单个字段
+fn update_annotation_field(conn: &Connection, key: &BookmarkKey, column: &str, value: &dyn rusqlite::ToSql) -> Result<(), String> {
+    let id = ensure_annotation(conn, key)?;
+    conn.execute(
+        &format!("UPDATE bookmark_annotations SET {} = ?1, updated_at = ?2 WHERE id = ?3", column),
+        params![value, Utc::now().to_rfc3339(), id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 读取全部书签本地信息，按书签标识索引
+fn load_annotations(conn: &Connection) -> Result<HashMap<BookmarkKey, Annotation>, String> {
+    let mut tag_stmt = conn
+        .prepare("SELECT bt.annotation_id, t.name FROM bookmark_tags bt JOIN tags t ON t.id = bt.tag_id ORDER BY t.name")
+        .map_err(|e| e.to_string())?;
+    let mut tags_by_id: HashMap<i64, Vec<String>> = HashMap::new();
+    let tag_rows = tag_stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?;
+    for row in tag_rows {
+        let (id, name) = row.map_err(|e| e.to_string())?;
+        tags_by_id.entry(id).or_default().push(name);
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT id, project_name, file_path, line_number, note, color, pinned, favorite, updated_at FROM bookmark_annotations")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            let id: i64 = row.get(0)?;
+            let key = BookmarkKey {
+                project_name: row.get(1)?,
+                file_path: row.get(2)?,
+                line_number: row.get(3)?,
+            };
+            let annotation = Annotation {
+                note: row.get(4)?,
+                color: row.get(5)?,
+                pinned: row.get(6)?,
+                favorite: row.get(7)?,
+                tags: Vec::new(),
+                updated_at: Some(row.get(8)?),
+            };
+            Ok((id, key, annotation))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut result = HashMap::new();
+    for row in rows {
+        let (id, key, mut annotation) = row.map_err(|e| e.to_string())?;
+        annotation.tags = tags_by_id.remove(&id).unwrap_or_default();
+        result.insert(key, annotation);
+    }
+    Ok(result)
+}
+
+/// 书签行号变化后（如重新定位），同步迁移其本地信息
+/// 目标行已有本地信息时返回错误，避免备注、颜色和标签被留在旧行上
+pub(crate) fn move_annotations(
+    conn: &Connection,
+    project_name: &str,
+    file_path: &str,
+    old_line: i32,
+    new_line: i32,
+) -> Result<(), String> {
+    let exists = |line: i32| -> Result<bool, String> {
+        conn.query_row(
+            "SELECT id FROM bookmark_annotations WHERE project_name = ?1 AND file_path = ?2 AND line_number = ?3",
+            params![project_name, file_path, line],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()
+        .map(|id| id.is_some())
+        .map_err(|e| e.to_string())
+    };
+    if old_line == new_line || !exists(old_line)? {
+        return Ok(());
+    }
+    if exists(new_line)? {
+        return Err(format!("第 {} 行的书签已有本地信息，无法迁移第 {} 行的备注和标签", new_line, old_line));
+    }
+
+    conn.execute(
+        "UPDATE bookmark_annotations SET line_number = ?1 WHERE project_name = ?2 AND file_path = ?3 AND line_number = ?4",
+        params![new_line, project_name, file_path, old_line],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 读取 workspace 中的书签并附带本地信息，可按标签、颜色、置顶、收藏筛选
+/// 置顶的书签排在最前
+#[tauri::command]
+pub fn list_annotated_bookmarks<R: Runtime>(
+    app: AppHandle<R>,
+    workspace_path: String,
+    filter: Option<AnnotationFilter>,
+) -> Result<Vec<AnnotatedBookmark>, String> {
+    let conn = open_db(&app)?;
+    let mut annotations = load_annotations(&conn)?;
+    let filter = filter.unwrap_or_default();
+
+    let mut result: Vec<AnnotatedBookmark> = read_bookmarks_from_workspace(workspace_path)?
+        .into_iter()
+        .map(|bookmark| {
+            let annotation = annotations.remove(&BookmarkKey::of(&bookmark)).unwrap_or_default();
+            AnnotatedBookmark { bookmark, annotation }
+        })
+        .filter(|b| filter.matches(&b.annotation))
+        .collect();
+
+    // 稳定排序，保持同组内原有的项目顺序
+    result.sort_by_key(|b| !b.annotation.pinned);
+    Ok(result)
+}
+
+/// 设置书签备注（Markdown）
+#[tauri::command]
+pub fn set_bookmark_note<R: Runtime>(app: AppHandle<R>, key: BookmarkKey, note: String) -> Result<(), String> {
+    change_annotation(&app, &key, None, "修改书签备注", |conn| update_annotation_field(conn, &key, "note", &note))
+}
+
+/// 设置书签颜色标签，传空清除
+#[tauri::command]
+pub fn set_bookmark_color<R: Runtime>(app: AppHandle<R>, key: BookmarkKey, color: Option<String>) -> Result<(), String> {
+    if let Some(c) = &color {
+        if !COLOR_LABELS.contains(&c.as_str()) {
+            return Err(format!("Unsupported color label: {}", c));
+        }
+    }
+    change_annotation(&app, &key, None, "修改书签颜色", |conn| update_annotation_field(conn, &key, "color", &color))
+}
+
+/// 置顶或取消置顶书签
+#[tauri::command]
+pub fn set_bookmark_pinned<R: Runtime>(app: AppHandle<R>, key: BookmarkKey, pinned: bool) -> Result<(), String> {
+    let summary = if pinned { "置顶书签" } else { "取消置顶书签" };
+    change_annotation(&app, &key, None, summary, |conn| update_annotation_field(conn, &key, "pinned", &pinned))
+}
+
+/// 收藏或取消收藏书签
+#[tauri::command]
+pub fn set_bookmark_favorite<R: Runtime>(app: AppHandle<R>, key: BookmarkKey, favorite: bool) -> Result<(), String> {
+    let summary = if favorite { "收藏书签" } else { "取消收藏书签" };
+    change_annotation(&app, &key, None, summary, |conn| update_annotation_field(conn, &key, "favorite", &favorite))
+}
+
+/// 为书签添加标签，标签不存在时自动创建
+#[tauri::command]
+pub fn add_bookmark_tag<R: Runtime>(app: AppHandle<R>, key: BookmarkKey, tag: String) -> Result<(), String> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err("Tag name cannot be empty".to_string());
+    }
+
+    change_annotation(&app, &key, Some(tag), &format!("添加标签 {}", tag), |conn| {
+        let annotation_id = ensure_annotation(conn, &key)?;
+        conn.execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", params![tag])
+            .map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR IGNORE INTO bookmark_tags (annotation_id, tag_id) SELECT ?1, id FROM tags WHERE name = ?2",
+            params![annotation_id, tag],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    })
+}
+
+/// 移除书签上的标签
+#[tauri::command]
+pub fn remove_bookmark_tag<R: Runtime>(app: AppHandle<R>, key: BookmarkKey, tag: String) -> Result<(), String> {
+    change_annotation(&app, &key, None, &format!("移除标签 {}", tag), |conn| {
+        conn.execute(
+            "DELETE FROM bookmark_tags
+             WHERE tag_id = (SELECT id FROM tags WHERE name = ?1)
+               AND annotation_id = (SELECT id FROM bookmark_annotations WHERE project_name = ?2 AND file_path = ?3 AND line_number = ?4)",
+            params![tag, key.project_name, key.file_path, key.line_number],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    })
+}
+
+/// 列出所有标签及使用次数
+#[tauri::command]
+pub fn list_tags<R: Runtime>(app: AppHandle<R>) -> Result<Vec<TagInfo>, String> {
+    let conn = open_db(&app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.id, t.name, COUNT(bt.annotation_id) FROM tags t
+             LEFT JOIN bookmark_tags bt ON bt.tag_id = t.id
+             GROUP BY t.id ORDER BY t.name",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(TagInfo {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                usage_count: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut tags = Vec::new();
+    for tag in rows {
+        tags.push(tag.map_err(|e| e.to_string())?);
+    }
+    Ok(tags)
+}
+
+/// 重命名标签
+#[tauri::command]
+pub fn rename_tag<R: Runtime>(app: AppHandle<R>, old_name: String, new_name: String) -> Result<(), String> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() {
+        return Err("Tag name cannot be empty".to_string());
+    }
+    let mut conn = open_db(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let exists: Option<i64> = tx
+        .query_row("SELECT id FROM tags WHERE name = ?1", params![new_name], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if exists.is_some() {
+        return Err(format!("Tag already exists: {}", new_name));
+    }
+
+    let before = snapshot_rows(&tx, "tags", "name = ?1", &[&old_name])?;
+    if before.is_empty() {
+        return Err(format!("Tag not found: {}", old_name));
+    }
+    tx.execute("UPDATE tags SET name = ?1 WHERE name = ?2", params![new_name, old_name])
+        .map_err(|e| e.to_string())?;
+    let after = snapshot_rows(&tx, "tags", "name = ?1", &[&new_name])?;
+    if let Some(op) = table_rows("tags", &["id"], before, after) {
+        record_operation(&tx, &format!("重命名标签 {} 为 {}", old_name, new_name), &op)?;
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// 删除标签（同时从所有书签上移除）
+#[tauri::command]
+pub fn delete_tag<R: Runtime>(app: AppHandle<R>, name: String) -> Result<(), String> {
+    let mut conn = open_db(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let tag = snapshot_rows(&tx, "tags", "name = ?1", &[&name])?;
+    if tag.is_empty() {
+        return Err(format!("Tag not found: {}", name));
+    }
+    let links = snapshot_rows(&tx, "bookmark_tags", "tag_id IN (SELECT id FROM tags WHERE name = ?1)", &[&name])?;
+
+    tx.execute("DELETE FROM tags WHERE name = ?1", params![name])
+        .map_err(|e| e.to_string())?;
+    // 先删除关联再删除标签，撤销时逆序执行，标签先于关联恢复
+    let operations: Vec<Operation> = [
+        table_rows("bookmark_tags", &["annotation_id", "tag_id"], links, Vec::new()),
+        table_rows("tags", &["id"], tag, Vec::new()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    record_operation(&tx, &format!("删除标签 {}", name), &Operation::Batch { operations })?;
+    tx.commit().map_err(|e| e.to_string())
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use tauri::{AppHandle, Runtime};
+
+use crate::preferences::{load_prefs, save_prefs};
+
+/// 加密备份文件头，同时作为附加认证数据
+const MAGIC: &[u8; 8] = b"IMKENC01";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + NONCE_LEN;
+/// 用于校验口令的已知明文
+const VERIFIER_PLAINTEXT: &[u8] = b"ideamark-backup-passphrase";
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// 解锁后的会话：口令与按盐缓存的派生密钥，避免每读一个备份都重新运行 Argon2
+struct Session {
+    passphrase: String,
+    /// 本次会话加密新备份时使用的盐
+    salt: [u8; SALT_LEN],
+    keys: HashMap<[u8; SALT_LEN], [u8; 32]>,
+}
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+/// 备份加密状态
+#[derive(serde::Serialize, Debug)]
+pub struct EncryptionStatus {
+    /// 新备份是否加密
+    pub enabled: bool,
+    /// 是否已设置过口令
+    pub configured: bool,
+    /// 本次运行中是否已解锁
+    pub unlocked: bool,
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).ok().filter(|p| p.len() == 2)?;
+            u8::from_str_radix(pair, 16).ok()
+        })
+        .collect()
+}
+
+/// 使用 Argon2id 从口令派生 256 位密钥
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| e.to_string())?;
+    Ok(key)
+}
+
+fn seal(key: &[u8; 32], salt: &[u8; SALT_LEN], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plaintext, aad: MAGIC })
+        .map_err(|_| "Encryption failed".to_string())?;
+
+    let mut out = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+fn open(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce = XNonce::from_slice(&data[MAGIC.len() + SALT_LEN..HEADER_LEN]);
+    XChaCha20Poly1305::new(key.into())
+        .decrypt(nonce, Payload { msg: &data[HEADER_LEN..], aad: MAGIC })
+        .map_err(|_| "口令错误或备份已损坏".to_string())
+}
+
+fn salt_of(data: &[u8]) -> [u8; SALT_LEN] {
+    let mut salt = [0u8; SALT_LEN];
+    salt.copy_from_slice(&data[MAGIC.len()..MAGIC.len() + SALT_LEN]);
+    salt
+}
+
+/// 判断内容是否为加密备份
+pub(crate) fn is_encrypted(data: &[u8]) -> bool {
+    data.len() > HEADER_LEN && data.starts_with(MAGIC)
+}
+
+/// 用给定口令解密，不依赖会话（用于校验口令）
+fn decrypt_with(passphrase: &str, data: &[u8]) -> Result<Vec<u8>, String> {
+    if !is_encrypted(data) {
+        return Err("Not an encrypted backup".to_string());
+    }
+    open(&derive_key(passphrase, &salt_of(data))?, data)
+}
+
+/// 用已解锁的会话加密内容
+fn encrypt_with_session(plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let session = SESSION.lock().map_err(|e| e.to_string())?;
+    let session = session.as_ref().ok_or("备份已锁定，请先输入口令解锁")?;
+    let key = session.keys.get(&session.salt).ok_or("备份已锁定，请先输入口令解锁")?;
+    seal(key, &session.salt, plaintext)
+}
+
+/// 用已解锁的会话解密内容
+pub(crate) fn decrypt_with_session(data: &[u8]) -> Result<Vec<u8>, String> {
+    if !is_encrypted(data) {
+        return Err("Not an encrypted backup".to_string());
+    }
+    let salt = salt_of(data);
+    let mut guard = SESSION.lock().map_err(|e| e.to_string())?;
+    let session = guard.as_mut().ok_or("备份已加密，请先输入口令解锁")?;
+    let key = match session.keys.get(&salt) {
+        Some(key) => *key,
+        None => {
+            let key = derive_key(&session.passphrase, &salt)?;
+            session.keys.insert(salt, key);
+            key
+        }
+    };
+    open(&key, data)
+}
+
+/// 会话是否已解锁
+pub(crate) fn is_unlocked() -> bool {
+    SESSION.lock().is_ok_and(|s| s.is_some())
+}
+
+/// 按偏好设置决定是否加密新备份的内容
+pub(crate) fn encrypt_backup_if_enabled<R: Runtime>(app: &AppHandle<R>, content: &[u8]) -> Result<Vec<u8>, String> {
+    if load_prefs(app)?.active().backup.encrypt {
+        encrypt_with_session(content)
+    } else {
+        Ok(content.to_vec())
+    }
+}
+
+/// 读取备份文件的明文内容，加密的备份需要先解锁
+pub(crate) fn read_backup_content(path: &Path) -> Result<String, String> {
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    let plain = if is_encrypted(&data) { decrypt_with_session(&data)? } else { data };
+    String::from_utf8(plain).map_err(|e| e.to_string())
+}
+
+fn start_session(passphrase: &str) -> Result<(), String> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+
+    let mut session = SESSION.lock().map_err(|e| e.to_string())?;
+    *session = Some(Session {
+        passphrase: passphrase.to_string(),
+        salt,
+        keys: HashMap::from([(salt, key)]),
+    });
+    Ok(())
+}
+
+/// 用保存的校验数据验证口令；尚未设置口令时返回 Ok(false)
+fn check_passphrase<R: Runtime>(app: &AppHandle<R>, passphrase: &str) -> Result<bool, String> {
+    let Some(verifier) = load_prefs(app)?.active().backup.encryption_verifier.clone() else {
+        return Ok(false);
+    };
+    let data = hex_decode(&verifier).ok_or("Invalid passphrase verifier")?;
+    match decrypt_with(passphrase, &data) {
+        Ok(plain) if plain == VERIFIER_PLAINTEXT => Ok(true),
+        _ => Err("口令错误".to_string()),
+    }
+}
+
+/// 获取备份加密状态
+#[tauri::command]
+pub fn get_backup_encryption_status<R: Runtime>(app: AppHandle<R>) -> Result<EncryptionStatus, String> {
+    let prefs = load_prefs(&app)?;
+    Ok(EncryptionStatus {
+        enabled: prefs.active().backup.encrypt,
+        configured: prefs.active().backup.encryption_verifier.is_some(),
+        unlocked: is_unlocked(),
+    })
+}
+
+/// 开启备份加密并解锁；已设置过口令时必须输入相同的口令
+#[tauri::command]
+pub fn enable_backup_encryption<R: Runtime>(app: AppHandle<R>, passphrase: String) -> Result<(), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!("口令至少需要 {} 个字符", MIN_PASSPHRASE_LEN));
+    }
+
+    let configured = check_passphrase(&app, &passphrase)?;
+    start_session(&passphrase)?;
+
+    let mut prefs = load_prefs(&app)?;
+    if !configured {
+        let verifier = encrypt_with_session(VERIFIER_PLAINTEXT)?;
+        prefs.active_mut().backup.encryption_verifier = Some(hex_encode(&verifier));
+    }
+    prefs.active_mut().backup.encrypt = true;
+    save_prefs(&app, &prefs)
+}
+
+/// 关闭新备份的加密；已加密的备份仍需口令才能读取
+#[tauri::command]
+pub fn disable_backup_encryption<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
+    let mut prefs = load_prefs(&app)?;
+    prefs.active_mut().backup.encrypt = false;
+    save_prefs(&app, &prefs)
+}
+
+/// 输入口令解锁加密备份，解锁状态保留到应用退出或手动锁定
+#[tauri::command]
+pub fn unlock_backups<R: Runtime>(app: AppHandle<R>, passphrase: String) -> Result<(), String> {
+    if !check_passphrase(&app, &passphrase)? {
+        return Err("尚未设置备份口令".to_string());
+    }
+    start_session(&passphrase)
+}
+
+/// 锁定加密备份，清除内存中的口令与密钥
+#[tauri::command]
+pub fn lock_backups() -> Result<(), String> {
+    let mut session = SESSION.lock().map_err(|e| e.to_string())?;
+    *session = None;
+    Ok(())
+}
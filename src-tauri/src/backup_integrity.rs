@@ -0,0 +1,201 @@
+use std::fs;
+use std::path::Path;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Runtime};
+
+use crate::backup_store::{delete_record, load_records, save_record, BackupRecord};
+use crate::backup_crypto::{decrypt_with_session, is_encrypted, is_unlocked};
+use crate::bookmark_manager::{
+    get_backup_dir, parse_backup_name, parse_global_workspace_content, parse_project_idea_content, ParsedBookmark,
+};
+use crate::db::open_db;
+
+/// 损坏的备份及原因
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct CorruptBackup {
+    pub id: String,
+    pub reason: String,
+}
+
+/// 备份校验报告
+#[derive(serde::Serialize, Default, Debug)]
+pub struct VerifyReport {
+    /// 检查的备份文件数量
+    pub checked: usize,
+    /// 校验和与书签数量都一致的备份
+    pub ok: Vec<String>,
+    pub corrupt: Vec<CorruptBackup>,
+    /// 没有元数据的备份文件
+    pub orphaned: Vec<String>,
+    /// 备份文件已不存在的元数据
+    pub dangling: Vec<String>,
+    /// 创建时没有记录校验和的旧备份
+    pub unverified: Vec<String>,
+    /// 加密且尚未解锁、只校验了校验和的备份
+    pub locked: Vec<String>,
+    /// 是否已修复元数据
+    pub repaired: bool,
+}
+
+/// 计算内容的 SHA-256，返回小写十六进制字符串
+pub(crate) fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 按文件格式解析备份内容中的书签
+pub(crate) fn parse_backup_content(content: &str) -> Vec<ParsedBookmark> {
+    if content.contains("BookmarksManager") {
+        parse_global_workspace_content(content)
+    } else {
+        parse_project_idea_content(content, "")
+    }
+}
+
+/// 粗略检查 XML 是否完整：根元素必须闭合，用于发现被截断的文件
+fn xml_looks_complete(content: &str) -> bool {
+    let mut rest = content.trim();
+    // 跳过 XML 声明与注释
+    while rest.starts_with("<?") || rest.starts_with("<!--") {
+        let close = if rest.starts_with("<?") { "?>" } else { "-->" };
+        match rest.find(close) {
+            Some(idx) => rest = rest[idx + close.len()..].trim_start(),
+            None => return false,
+        }
+    }
+    let Some(tag) = rest.strip_prefix('<') else {
+        return false;
+    };
+    let name: String = tag
+        .chars()
+        .take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+        .collect();
+    if name.is_empty() {
+        return false;
+    }
+
+    let open_end = tag.find('>').unwrap_or(0);
+    let self_closing = tag[..open_end].ends_with('/');
+    let trimmed = rest.trim_end();
+    if self_closing && trimmed.len() == open_end + 2 {
+        return true;
+    }
+    trimmed.ends_with(&format!("</{}>", name))
+}
+
+/// 检查单个备份文件，返回 (损坏原因, 实际 SHA-256, 实际书签)
+/// 加密备份在未解锁时只校验 SHA-256，书签为 None
+fn check_backup(path: &Path, entry: Option<&BackupRecord>) -> (Option<String>, String, Option<Vec<ParsedBookmark>>) {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => return (Some(format!("无法读取：{}", e)), String::new(), None),
+    };
+    let hash = sha256_hex(&bytes);
+
+    if let Some(expected) = entry.and_then(|e| e.sha256.as_deref()) {
+        if expected != hash {
+            return (Some("校验和不一致，文件已被修改或损坏".to_string()), hash, None);
+        }
+    }
+    let bytes = if is_encrypted(&bytes) {
+        if !is_unlocked() {
+            return (None, hash, None);
+        }
+        match decrypt_with_session(&bytes) {
+            Ok(plain) => plain,
+            Err(e) => return (Some(e), hash, None),
+        }
+    } else {
+        bytes
+    };
+    let Ok(content) = String::from_utf8(bytes) else {
+        return (Some("不是有效的 UTF-8 文本".to_string()), hash, None);
+    };
+    if !xml_looks_complete(&content) {
+        return (Some("XML 不完整，文件可能被截断".to_string()), hash, None);
+    }
+
+    let bookmarks = parse_backup_content(&content);
+    if let Some(expected) = entry.and_then(|e| e.bookmark_count) {
+        if expected != bookmarks.len() {
+            let reason = format!("书签数量不一致：记录 {} 个，实际解析到 {} 个", expected, bookmarks.len());
+            return (Some(reason), hash, Some(bookmarks));
+        }
+    }
+    (None, hash, Some(bookmarks))
+}
+
+/// 重新计算所有备份的校验和并重新解析，报告损坏、孤立（无元数据）和悬空（无文件）的条目
+/// repair 为 true 时修复元数据：为孤立文件和旧备份补充记录，删除悬空条目；损坏的文件不会被改动
+#[tauri::command]
+pub fn verify_backups<R: Runtime>(app: AppHandle<R>, repair: bool) -> Result<VerifyReport, String> {
+    let backup_dir = get_backup_dir(&app)?;
+    let mut conn = open_db(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let records = load_records(&tx, false)?;
+    let mut report = VerifyReport::default();
+    let mut seen = Vec::new();
+
+    if backup_dir.exists() {
+        for entry in fs::read_dir(&backup_dir).map_err(|e| e.to_string())?.flatten() {
+            let path = entry.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if !path.is_file() || parse_backup_name(&name).is_none() {
+                continue;
+            }
+            report.checked += 1;
+            seen.push(name.clone());
+
+            let existing = records.get(&name);
+            let (problem, hash, bookmarks) = check_backup(&path, existing);
+            let legacy = existing.is_some_and(|e| e.sha256.is_none());
+
+            match (&problem, existing) {
+                (Some(reason), _) => report.corrupt.push(CorruptBackup { id: name.clone(), reason: reason.clone() }),
+                (None, None) => report.orphaned.push(name.clone()),
+                (None, Some(_)) if legacy => report.unverified.push(name.clone()),
+                (None, Some(_)) => report.ok.push(name.clone()),
+            }
+            if problem.is_none() && bookmarks.is_none() {
+                report.locked.push(name.clone());
+            }
+
+            let Some(bookmarks) = bookmarks else {
+                continue;
+            };
+            if repair && problem.is_none() && (existing.is_none() || legacy) {
+                let mut record = existing.cloned().unwrap_or_else(|| BackupRecord::from_file_name(&name));
+                if record.projects.is_empty() {
+                    for b in &bookmarks {
+                        if !b.project_name.is_empty() && !record.projects.contains(&b.project_name) {
+                            record.projects.push(b.project_name.clone());
+                        }
+                    }
+                }
+                record.size = fs::metadata(&path).ok().map(|m| m.len() as i64);
+                record.sha256 = Some(hash);
+                record.bookmark_count = Some(bookmarks.len());
+                save_record(&tx, &record)?;
+            }
+        }
+    }
+
+    report.dangling = records.keys().filter(|id| !seen.contains(id)).cloned().collect();
+    report.dangling.sort();
+
+    if repair {
+        for id in &report.dangling {
+            delete_record(&tx, id)?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        report.repaired = true;
+    }
+
+    println!(
+        "Verified {} backups: {} corrupt, {} orphaned, {} dangling",
+        report.checked,
+        report.corrupt.len(),
+        report.orphaned.len(),
+        report.dangling.len()
+    );
+    Ok(report)
+}
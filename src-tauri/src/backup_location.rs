@@ -0,0 +1,323 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use chrono::Utc;
+use tauri::{AppHandle, Runtime};
+
+use crate::backup_integrity::sha256_hex;
+use crate::backup_store::{get_record, save_record, BackupRecord};
+use crate::bookmark_manager::{default_backup_dir, get_backup_dir, parse_backup_name};
+use crate::db::open_db;
+use crate::preferences::{load_prefs, save_prefs};
+
+/// 备份目录中存放旁路元数据的子目录，随备份一起被同步盘同步
+const SIDECAR_DIR: &str = ".ideamark";
+
+/// 与备份文件放在一起的元数据，其它机器据此识别备份来源
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+struct Sidecar {
+    machine_id: String,
+    host: String,
+    source_path: Option<String>,
+    ide_version: Option<String>,
+    size: Option<i64>,
+    sha256: Option<String>,
+    bookmark_count: Option<usize>,
+    #[serde(default)]
+    projects: Vec<String>,
+    created_at: String,
+}
+
+/// 当前备份目录信息
+#[derive(serde::Serialize, Debug)]
+pub struct BackupLocation {
+    pub path: String,
+    /// 是否为应用数据目录下的默认位置
+    pub is_default: bool,
+    pub backup_count: usize,
+}
+
+/// 迁移备份目录的结果
+#[derive(serde::Serialize, Debug)]
+pub struct BackupRelocationReport {
+    pub from: String,
+    pub to: String,
+    /// 复制并校验后移动的备份数量
+    pub moved: usize,
+    /// 目标目录中已存在相同内容、直接跳过的备份数量
+    pub skipped_identical: usize,
+}
+
+/// 同步目录中由其它机器写入的备份
+#[derive(serde::Serialize, Debug)]
+pub struct ForeignBackup {
+    pub id: String,
+    pub origin_host: String,
+    pub origin_machine: String,
+    pub created_at: String,
+    pub projects: Vec<String>,
+    pub bookmark_count: Option<usize>,
+    /// 本机数据库中是否已有该备份的记录
+    pub known: bool,
+}
+
+fn host_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| {
+            Command::new("hostname")
+                .output()
+                .ok()
+                .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+        })
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// 本机标识与主机名；标识首次使用时生成并保存在偏好设置中
+pub(crate) fn machine_identity<R: Runtime>(app: &AppHandle<R>) -> Result<(String, String), String> {
+    let host = host_name();
+    let mut prefs = load_prefs(app)?;
+    if let Some(id) = &prefs.machine_id {
+        return Ok((id.clone(), host));
+    }
+
+    let seed = format!("{}-{}-{}", host, Utc::now().timestamp_nanos_opt().unwrap_or_default(), std::process::id());
+    let id = sha256_hex(seed.as_bytes())[..16].to_string();
+    prefs.machine_id = Some(id.clone());
+    if let Err(e) = save_prefs(app, &prefs) {
+        println!("Failed to save machine id: {}", e);
+    }
+    Ok((id, host))
+}
+
+fn sidecar_path(backup_dir: &Path, id: &str) -> PathBuf {
+    backup_dir.join(SIDECAR_DIR).join(format!("{}.json", id))
+}
+
+/// 在备份目录中写入备份的旁路元数据
+pub(crate) fn write_sidecar<R: Runtime>(app: &AppHandle<R>, backup_dir: &Path, record: &BackupRecord) -> Result<(), String> {
+    let (machine_id, host) = machine_identity(app)?;
+    // 加密备份不在明文旁路文件中暴露原始路径与项目名
+    let encrypted = load_prefs(app)?.active().backup.encrypt;
+    let sidecar = Sidecar {
+        machine_id: record.origin_machine.clone().unwrap_or(machine_id),
+        host: record.origin_host.clone().unwrap_or(host),
+        source_path: if encrypted { None } else { record.source_path.clone() },
+        ide_version: record.ide_version.clone(),
+        size: record.size,
+        sha256: record.sha256.clone(),
+        bookmark_count: record.bookmark_count,
+        projects: if encrypted { Vec::new() } else { record.projects.clone() },
+        created_at: record.created_at.clone(),
+    };
+
+    let path = sidecar_path(backup_dir, &record.id);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(&sidecar).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| e.to_string())
+}
+
+fn read_sidecar(backup_dir: &Path, id: &str) -> Option<Sidecar> {
+    let content = fs::read_to_string(sidecar_path(backup_dir, id)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// 列出目录中的备份文件名
+fn list_backup_ids(dir: &Path) -> Result<Vec<String>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut ids = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())?.flatten() {
+        let path = entry.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if path.is_file() && parse_backup_name(&name).is_some() {
+            ids.push(name);
+        }
+    }
+    ids.sort();
+    Ok(ids)
+}
+
+/// 复制文件并回读校验内容；目标已存在且内容相同时返回 Ok(false)
+fn copy_verified(from: &Path, to: &Path) -> Result<bool, String> {
+    let content = fs::read(from).map_err(|e| e.to_string())?;
+    let hash = sha256_hex(&content);
+    if to.exists() {
+        let existing = fs::read(to).map_err(|e| e.to_string())?;
+        if sha256_hex(&existing) == hash {
+            return Ok(false);
+        }
+        return Err(format!("目标目录中已存在不同内容的同名文件：{}", to.display()));
+    }
+
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(to, &content).map_err(|e| e.to_string())?;
+    let written = fs::read(to).map_err(|e| e.to_string())?;
+    if sha256_hex(&written) != hash {
+        let _ = fs::remove_file(to);
+        return Err(format!("复制后校验失败：{}", to.display()));
+    }
+    Ok(true)
+}
+
+/// 获取当前备份目录
+#[tauri::command]
+pub fn get_backup_location<R: Runtime>(app: AppHandle<R>) -> Result<BackupLocation, String> {
+    let dir = get_backup_dir(&app)?;
+    Ok(BackupLocation {
+        path: dir.to_string_lossy().to_string(),
+        is_default: load_prefs(&app)?.active().backup.dir.is_none(),
+        backup_count: list_backup_ids(&dir)?.len(),
+    })
+}
+
+/// 更改备份目录，path 为空时恢复默认位置
+/// move_existing 为 true 时先把现有备份和旁路元数据复制到新目录并逐个校验，全部成功后才切换并删除旧文件；
+/// 任何一步失败都会撤回已复制的文件，保持原目录不变
+#[tauri::command]
+pub fn set_backup_location<R: Runtime>(
+    app: AppHandle<R>,
+    path: Option<String>,
+    move_existing: bool,
+) -> Result<BackupRelocationReport, String> {
+    let old_dir = get_backup_dir(&app)?;
+    let custom = path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if custom.as_ref().is_some_and(|p| !Path::new(p).is_absolute()) {
+        return Err("备份目录必须是绝对路径".to_string());
+    }
+    let new_dir = match custom.as_ref() {
+        Some(dir) => PathBuf::from(dir),
+        None => default_backup_dir(&app)?,
+    };
+
+    let mut report = BackupRelocationReport {
+        from: old_dir.to_string_lossy().to_string(),
+        to: new_dir.to_string_lossy().to_string(),
+        moved: 0,
+        skipped_identical: 0,
+    };
+
+    // 确认新目录可写
+    fs::create_dir_all(&new_dir).map_err(|e| e.to_string())?;
+    let probe = new_dir.join(".ideamark_write_test");
+    fs::write(&probe, b"ok").map_err(|e| format!("备份目录不可写：{}", e))?;
+    let _ = fs::remove_file(&probe);
+
+    let same_dir = match (fs::canonicalize(&old_dir), fs::canonicalize(&new_dir)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    };
+
+    let mut moved_sources = Vec::new();
+    if move_existing && !same_dir {
+        let mut copied = Vec::new();
+        let result = (|| -> Result<(), String> {
+            for id in list_backup_ids(&old_dir)? {
+                let mut files = vec![(old_dir.join(&id), new_dir.join(&id))];
+                let sidecar = sidecar_path(&old_dir, &id);
+                if sidecar.exists() {
+                    files.push((sidecar, sidecar_path(&new_dir, &id)));
+                }
+                for (i, (from, to)) in files.into_iter().enumerate() {
+                    let copied_new = copy_verified(&from, &to)?;
+                    if copied_new {
+                        copied.push(to);
+                    }
+                    if i == 0 {
+                        if copied_new {
+                            report.moved += 1;
+                        } else {
+                            report.skipped_identical += 1;
+                        }
+                    }
+                    moved_sources.push(from);
+                }
+            }
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            for file in &copied {
+                let _ = fs::remove_file(file);
+            }
+            return Err(e);
+        }
+    }
+
+    let mut prefs = load_prefs(&app)?;
+    prefs.active_mut().backup.dir = custom;
+    save_prefs(&app, &prefs)?;
+
+    for file in &moved_sources {
+        let _ = fs::remove_file(file);
+    }
+    if !moved_sources.is_empty() {
+        // 目录为空时顺便清理
+        let _ = fs::remove_dir(old_dir.join(SIDECAR_DIR));
+        let _ = fs::remove_dir(&old_dir);
+    }
+
+    println!(
+        "Backup location changed to {:?}: moved {}, skipped {}",
+        new_dir, report.moved, report.skipped_identical
+    );
+    Ok(report)
+}
+
+/// 查找备份目录中由其它机器写入的备份（依据旁路元数据中的机器标识）
+/// import 为 true 时为本机尚无记录的备份创建记录，使其出现在备份列表的筛选和校验中
+#[tauri::command]
+pub fn scan_foreign_backups<R: Runtime>(app: AppHandle<R>, import: bool) -> Result<Vec<ForeignBackup>, String> {
+    let (machine_id, _) = machine_identity(&app)?;
+    let backup_dir = get_backup_dir(&app)?;
+    let conn = open_db(&app)?;
+    let mut foreign = Vec::new();
+
+    for id in list_backup_ids(&backup_dir)? {
+        let Some(sidecar) = read_sidecar(&backup_dir, &id) else {
+            continue;
+        };
+        if sidecar.machine_id == machine_id {
+            continue;
+        }
+
+        let known = get_record(&conn, &id)?.is_some();
+        if import && !known {
+            let record = BackupRecord {
+                source_path: sidecar.source_path.clone(),
+                ide_version: sidecar.ide_version.clone(),
+                size: sidecar.size,
+                sha256: sidecar.sha256.clone(),
+                bookmark_count: sidecar.bookmark_count,
+                projects: sidecar.projects.clone(),
+                created_at: sidecar.created_at.clone(),
+                origin_machine: Some(sidecar.machine_id.clone()),
+                origin_host: Some(sidecar.host.clone()),
+                ..BackupRecord::from_file_name(&id)
+            };
+            save_record(&conn, &record)?;
+        }
+
+        foreign.push(ForeignBackup {
+            id,
+            origin_host: sidecar.host,
+            origin_machine: sidecar.machine_id,
+            created_at: sidecar.created_at,
+            projects: sidecar.projects,
+            bookmark_count: sidecar.bookmark_count,
+            known: known || import,
+        });
+    }
+
+    println!("Found {} backups from other machines", foreign.len());
+    Ok(foreign)
+}
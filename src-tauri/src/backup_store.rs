@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::bookmark_manager::{get_backup_dir, get_backup_trash_dir};
+use crate::db::open_db;
+
+/// 数据库中的一条备份记录，id 为备份文件名
+#[derive(Clone, Debug, Default)]
+pub(crate) struct BackupRecord {
+    pub(crate) id: String,
+    pub(crate) original_file_name: String,
+    /// 被备份的原始文件路径
+    pub(crate) source_path: Option<String>,
+    /// 备份来源的 IDE 版本目录名，如 IntelliJIdea2025.1
+    pub(crate) ide_version: Option<String>,
+    pub(crate) size: Option<i64>,
+    /// 创建时计算的 SHA-256，旧备份没有
+    pub(crate) sha256: Option<String>,
+    /// 创建时解析到的书签数量，旧备份没有
+    pub(crate) bookmark_count: Option<usize>,
+    pub(crate) projects: Vec<String>,
+    pub(crate) label: Option<String>,
+    pub(crate) note: Option<String>,
+    pub(crate) pinned: bool,
+    /// 创建时间 (RFC 3339)
+    pub(crate) created_at: String,
+    /// 移入回收区的时间 (RFC 3339)，不在回收区时为空
+    pub(crate) deleted_at: Option<String>,
+    /// 创建该备份的机器标识
+    pub(crate) origin_machine: Option<String>,
+    /// 创建该备份的主机名
+    pub(crate) origin_host: Option<String>,
+}
+
+impl BackupRecord {
+    /// 为没有记录的备份文件生成默认记录，原文件名与创建时间取自文件名
+    pub(crate) fn from_file_name(id: &str) -> Self {
+        let mut parts = id.splitn(4, '_');
+        let (date, time, _, original) = (parts.next(), parts.next(), parts.next(), parts.next());
+        let created_at = NaiveDateTime::parse_from_str(
+            &format!("{}_{}", date.unwrap_or_default(), time.unwrap_or_default()),
+            "%Y%m%d_%H%M%S",
+        )
+        .ok()
+        .and_then(|dt| Local.from_local_datetime(&dt).single())
+        .map(|dt| dt.with_timezone(&Utc).to_rfc3339())
+        .unwrap_or_else(|| Utc::now().to_rfc3339());
+
+        BackupRecord {
+            id: id.to_string(),
+            original_file_name: original.unwrap_or(id).to_string(),
+            created_at,
+            ..Default::default()
+        }
+    }
+}
+
+const RECORD_COLUMNS: &str = "id, original_file_name, source_path, ide_version, size, sha256, bookmark_count, \
+     projects, label, note, pinned, created_at, deleted_at, origin_machine, origin_host";
+
+fn row_to_record(row: &rusqlite::Row) -> rusqlite::Result<BackupRecord> {
+    let projects: String = row.get(7)?;
+    Ok(BackupRecord {
+        id: row.get(0)?,
+        original_file_name: row.get(1)?,
+        source_path: row.get(2)?,
+        ide_version: row.get(3)?,
+        size: row.get(4)?,
+        sha256: row.get(5)?,
+        bookmark_count: row.get::<_, Option<i64>>(6)?.map(|c| c as usize),
+        projects: serde_json::from_str(&projects).unwrap_or_default(),
+        label: row.get(8)?,
+        note: row.get(9)?,
+        pinned: row.get(10)?,
+        created_at: row.get(11)?,
+        deleted_at: row.get(12)?,
+        origin_machine: row.get(13)?,
+        origin_host: row.get(14)?,
+    })
+}
+
+pub(crate) fn get_record(conn: &Connection, id: &str) -> Result<Option<BackupRecord>, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM backups WHERE id = ?1", RECORD_COLUMNS),
+        params![id],
+        row_to_record,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// 读取全部备份记录；trashed 为 true 时只读取回收区中的记录，否则只读取正常备份
+pub(crate) fn load_records(conn: &Connection, trashed: bool) -> Result<HashMap<String, BackupRecord>, String> {
+    let sql = format!(
+        "SELECT {} FROM backups WHERE deleted_at IS {} NULL",
+        RECORD_COLUMNS,
+        if trashed { "NOT" } else { "" }
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_record).map_err(|e| e.to_string())?;
+
+    let mut records = HashMap::new();
+    for record in rows {
+        let record = record.map_err(|e| e.to_string())?;
+        records.insert(record.id.clone(), record);
+    }
+    Ok(records)
+}
+
+/// 写入备份记录，同 ID 的记录整体替换
+pub(crate) fn save_record(conn: &Connection, r: &BackupRecord) -> Result<(), String> {
+    let projects = serde_json::to_string(&r.projects).map_err(|e| e.to_string())?;
+    conn.execute(
+        &format!(
+            "INSERT OR REPLACE INTO backups ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            RECORD_COLUMNS
+        ),
+        params![
+            r.id,
+            r.original_file_name,
+            r.source_path,
+            r.ide_version,
+            r.size,
+            r.sha256,
+            r.bookmark_count.map(|c| c as i64),
+            projects,
+            r.label,
+            r.note,
+            r.pinned,
+            r.created_at,
+            r.deleted_at,
+            r.origin_machine,
+            r.origin_host
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 读取、修改并写回一条备份记录，记录不存在时从文件名生成
+/// 在调用方的连接上执行，需要与其它写入保持一致时由调用方传入事务
+pub(crate) fn update_record(
+    conn: &Connection,
+    id: &str,
+    update: impl FnOnce(&mut BackupRecord),
+) -> Result<BackupRecord, String> {
+    let mut record = get_record(conn, id)?.unwrap_or_else(|| BackupRecord::from_file_name(id));
+    update(&mut record);
+    save_record(conn, &record)?;
+    Ok(record)
+}
+
+pub(crate) fn delete_record(conn: &Connection, id: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM backups WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 从被备份文件的路径推断 IDE 版本目录名（如 .../JetBrains/IntelliJIdea2025.1/workspace/x.xml）
+pub(crate) fn detect_ide_version(source_path: &Path) -> Option<String> {
+    source_path.ancestors().filter_map(|p| p.file_name()).find_map(|name| {
+        let name = name.to_string_lossy();
+        let digits_at = name.find(|c: char| c.is_ascii_digit())?;
+        let (product, version) = name.split_at(digits_at);
+        let looks_like_version = version.len() >= 6
+            && version[..4].chars().all(|c| c.is_ascii_digit())
+            && version[4..].starts_with('.');
+        (!product.is_empty() && product.chars().all(|c| c.is_ascii_alphabetic()) && looks_like_version)
+            .then(|| name.to_string())
+    })
+}
+
+/// 兼容旧版 backup_meta.json：条目可能直接是项目名列表
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum LegacyEntry {
+    Projects(Vec<String>),
+    Full {
+        #[serde(default)]
+        projects: Vec<String>,
+        #[serde(default)]
+        label: Option<String>,
+        #[serde(default)]
+        note: Option<String>,
+        #[serde(default)]
+        pinned: bool,
+        #[serde(default)]
+        sha256: Option<String>,
+        #[serde(default)]
+        bookmark_count: Option<usize>,
+    },
+}
+
+#[derive(serde::Deserialize)]
+struct LegacyTrashEntry {
+    entry: LegacyEntry,
+    deleted_at: String,
+}
+
+#[derive(serde::Deserialize)]
+struct LegacyMeta {
+    #[serde(default)]
+    entries: HashMap<String, LegacyEntry>,
+    #[serde(default)]
+    trash: HashMap<String, LegacyTrashEntry>,
+}
+
+fn legacy_to_record(id: &str, entry: LegacyEntry, file: &Path) -> BackupRecord {
+    let mut record = BackupRecord::from_file_name(id);
+    record.size = fs::metadata(file).ok().map(|m| m.len() as i64);
+    match entry {
+        LegacyEntry::Projects(projects) => record.projects = projects,
+        LegacyEntry::Full { projects, label, note, pinned, sha256, bookmark_count } => {
+            record.projects = projects;
+            record.label = label;
+            record.note = note;
+            record.pinned = pinned;
+            record.sha256 = sha256;
+            record.bookmark_count = bookmark_count;
+        }
+    }
+    record
+}
+
+/// 一次性将 backup_meta.json 导入数据库，成功后改名为 backup_meta.json.migrated
+/// 解析失败时保留原文件并返回错误，不会丢弃任何元数据；数据库中已有的记录优先
+pub(crate) fn migrate_backup_meta<R: Runtime>(app: &AppHandle<R>) -> Result<usize, String> {
+    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let meta_path = app_dir.join("backup_meta.json");
+    if !meta_path.exists() {
+        return Ok(0);
+    }
+
+    let content = fs::read_to_string(&meta_path).map_err(|e| e.to_string())?;
+    let legacy: LegacyMeta = serde_json::from_str(&content)
+        .map_err(|e| format!("backup_meta.json 无法解析，已保留原文件：{}", e))?;
+
+    let mut conn = open_db(app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut imported = 0;
+
+    let backup_dir = get_backup_dir(app)?;
+    for (id, entry) in legacy.entries {
+        if get_record(&tx, &id)?.is_none() {
+            save_record(&tx, &legacy_to_record(&id, entry, &backup_dir.join(&id)))?;
+            imported += 1;
+        }
+    }
+    let trash_dir = get_backup_trash_dir(app)?;
+    for (id, trashed) in legacy.trash {
+        if get_record(&tx, &id)?.is_none() {
+            let mut record = legacy_to_record(&id, trashed.entry, &trash_dir.join(&id));
+            record.deleted_at = Some(trashed.deleted_at);
+            save_record(&tx, &record)?;
+            imported += 1;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    fs::rename(&meta_path, app_dir.join("backup_meta.json.migrated")).map_err(|e| e.to_string())?;
+    println!("Migrated {} backup records from backup_meta.json", imported);
+    Ok(imported)
+}
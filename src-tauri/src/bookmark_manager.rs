@@ -2,7 +2,18 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use tauri::{AppHandle, Runtime, Manager};
-use chrono::Local;
+use chrono::{DateTime, Duration, Local, Utc};
+use rusqlite::Connection;
+
+use crate::backup_crypto::{encrypt_backup_if_enabled, read_backup_content};
+use crate::backup_integrity::{parse_backup_content, sha256_hex};
+use crate::backup_location::{machine_identity, write_sidecar};
+use crate::backup_store::{
+    delete_record, detect_ide_version, get_record, load_records, save_record, update_record, BackupRecord,
+};
+use crate::db::open_db;
+use crate::journal::{self, record_operation, snapshot_rows, table_rows, Operation};
+use crate::preferences::load_prefs;
 
 /// 用于直接展示的书签结构，包含用户友好的字段
 #[derive(serde::Serialize, Clone, Debug)]
@@ -44,6 +55,80 @@ pub struct BackupFile {
     pub timestamp: String,
     pub path: String,
     pub projects: Vec<String>,  // 备份包含的项目名列表
+    /// 用户标签
+    pub label: Option<String>,
+    /// 用户备注
+    pub note: Option<String>,
+    /// 置顶的备份不会被自动清理
+    pub pinned: bool,
+    /// 创建时记录的书签数量
+    pub bookmark_count: Option<usize>,
+    /// 文件大小（字节）
+    pub size: Option<i64>,
+    pub sha256: Option<String>,
+    /// 备份来源的 IDE 版本目录名
+    pub ide_version: Option<String>,
+    /// 被备份的原始文件路径
+    pub source_path: Option<String>,
+    /// 创建该备份的主机名
+    pub origin_host: Option<String>,
+    /// 是否由其它机器写入（同步目录）
+    pub foreign: bool,
+}
+
+/// 备份列表筛选条件，所有条件均可省略
+#[derive(serde::Deserialize, Default)]
+pub struct BackupFilter {
+    /// 标签包含该字符串（不区分大小写）
+    pub label: Option<String>,
+    /// 备份包含该项目
+    pub project: Option<String>,
+    /// 备份时间下限（含），YYYY-MM-DD 或 YYYY-MM-DD HH:MM:SS
+    pub from: Option<String>,
+    /// 备份时间上限（含），YYYY-MM-DD 或 YYYY-MM-DD HH:MM:SS
+    pub to: Option<String>,
+    #[serde(default)]
+    pub pinned_only: bool,
+}
+
+impl BackupFilter {
+    fn matches(&self, b: &BackupFile) -> bool {
+        if let Some(label) = self.label.as_deref().map(str::to_lowercase).filter(|l| !l.is_empty()) {
+            if !b.label.as_deref().is_some_and(|l| l.to_lowercase().contains(&label)) {
+                return false;
+            }
+        }
+        if let Some(project) = &self.project {
+            if !b.projects.contains(project) {
+                return false;
+            }
+        }
+        if let Some(from) = &self.from {
+            if b.timestamp.as_str() < from.as_str() {
+                return false;
+            }
+        }
+        if let Some(to) = &self.to {
+            // 只比较与上限等长的前缀，使 YYYY-MM-DD 包含当天的全部备份
+            let prefix = b.timestamp.get(..to.len()).unwrap_or(&b.timestamp);
+            if prefix > to.as_str() {
+                return false;
+            }
+        }
+        !self.pinned_only || b.pinned
+    }
+}
+
+/// 回收区中的备份
+#[derive(serde::Serialize, Clone)]
+pub struct TrashedBackup {
+    pub id: String,
+    pub original_file_name: String,
+    pub timestamp: String,
+    /// 移入回收区的时间 (RFC 3339)
+    pub deleted_at: String,
+    pub path: String,
+    pub projects: Vec<String>,
 }
 
 // Helper to get the base JetBrains roaming directory
@@ -53,8 +138,13 @@ fn get_jetbrains_roaming_dir() -> Option<PathBuf> {
 }
 
 #[tauri::command]
-pub fn find_idea_dirs() -> Result<Vec<IdeaVersion>, String> {
-    let jb_dir = get_jetbrains_roaming_dir().ok_or("Could not find AppData directory")?;
+pub fn find_idea_dirs<R: Runtime>(app: AppHandle<R>) -> Result<Vec<IdeaVersion>, String> {
+    let scan = load_prefs(&app)?.scan;
+    let jb_dir = scan
+        .jetbrains_dir
+        .map(PathBuf::from)
+        .or_else(get_jetbrains_roaming_dir)
+        .ok_or("Could not find AppData directory")?;
     
     if !jb_dir.exists() {
         return Ok(Vec::new());
@@ -68,7 +158,7 @@ pub fn find_idea_dirs() -> Result<Vec<IdeaVersion>, String> {
         let path = entry.path();
         if path.is_dir() {
             if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                if name.starts_with("IntelliJIdea") {
+                if scan.product_prefixes.iter().any(|p| name.starts_with(p.as_str())) {
                     let workspace_path = path.join("workspace");
                      // Even if workspace subdir doesn't exist yet, we list it so user knows it was detected
                     versions.push(IdeaVersion {
@@ -113,39 +203,141 @@ pub fn list_workspace_files(workspace_path: String) -> Result<Vec<WorkspaceFile>
     Ok(files)
 }
 
-fn get_backup_dir<R: Runtime>(app: &AppHandle<R>) -> PathBuf {
+/// 备份目录：优先使用当前档案中的自定义目录
+pub(crate) fn get_backup_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    if let Some(dir) = load_prefs(app)?.active().backup.dir.clone() {
+        return Ok(PathBuf::from(dir));
+    }
+    default_backup_dir(app)
+}
+
+/// 默认备份目录，不同档案的数据库命名空间各用一个
+pub(crate) fn default_backup_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
     let app_dir = app.path().app_data_dir().expect("failed to get app data dir");
-    app_dir.join("backups")
+    Ok(app_dir.join(load_prefs(app)?.active().namespaced("backups")))
 }
 
-fn get_backup_meta_path<R: Runtime>(app: &AppHandle<R>) -> PathBuf {
+/// 备份回收区，与默认备份目录一样按档案的数据库命名空间区分
+pub(crate) fn get_backup_trash_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
     let app_dir = app.path().app_data_dir().expect("failed to get app data dir");
-    app_dir.join("backup_meta.json")
+    Ok(app_dir.join(load_prefs(app)?.active().namespaced("backup_trash")))
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Default)]
-struct BackupMeta {
-    // 备份ID -> 项目名列表
-    entries: std::collections::HashMap<String, Vec<String>>,
+/// 移动文件；备份目录位于其它磁盘或网络位置时 rename 会失败，改为复制后删除
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map_err(|e| e.to_string())?;
+    fs::remove_file(from).map_err(|e| e.to_string())
 }
 
-fn load_backup_meta<R: Runtime>(app: &AppHandle<R>) -> BackupMeta {
-    let meta_path = get_backup_meta_path(app);
-    if meta_path.exists() {
-        if let Ok(content) = fs::read_to_string(&meta_path) {
-            if let Ok(meta) = serde_json::from_str(&content) {
-                return meta;
-            }
+/// 将备份移入回收区并标记其记录，返回 (回收区路径, 项目名列表)
+/// 记录在 conn 上先写入、文件随后移动；文件移动失败时调用方的事务回滚，两者保持一致
+pub(crate) fn trash_backup<R: Runtime>(
+    app: &AppHandle<R>,
+    conn: &Connection,
+    backup: &Path,
+) -> Result<(PathBuf, Vec<String>), String> {
+    let file_name = backup.file_name().ok_or("Invalid file name")?.to_string_lossy().to_string();
+    let trash_dir = get_backup_trash_dir(app)?;
+    fs::create_dir_all(&trash_dir).map_err(|e| e.to_string())?;
+
+    let record = update_record(conn, &file_name, |r| r.deleted_at = Some(Utc::now().to_rfc3339()))?;
+    let trash_path = trash_dir.join(&file_name);
+    // 同名备份之前被删除过时，用新删除的版本替换
+    if trash_path.exists() {
+        fs::remove_file(&trash_path).map_err(|e| e.to_string())?;
+    }
+    move_file(backup, &trash_path)?;
+    Ok((trash_path, record.projects))
+}
+
+/// 将回收区中的备份移回原位置并恢复记录；记录缺失时只恢复项目名
+/// 与 trash_backup 相同，先在 conn 上写入记录再移动文件
+pub(crate) fn restore_backup_from_trash(
+    conn: &Connection,
+    trash_path: &Path,
+    backup_path: &Path,
+    projects: Vec<String>,
+) -> Result<(), String> {
+    if !trash_path.exists() {
+        return Err("Backup is no longer in the trash".to_string());
+    }
+    let file_name = backup_path.file_name().ok_or("Invalid file name")?.to_string_lossy().to_string();
+    update_record(conn, &file_name, |r| {
+        if r.projects.is_empty() {
+            r.projects = projects;
         }
+        r.deleted_at = None;
+    })?;
+
+    if let Some(parent) = backup_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
-    BackupMeta::default()
+    move_file(trash_path, backup_path)
 }
 
-fn save_backup_meta<R: Runtime>(app: &AppHandle<R>, meta: &BackupMeta) -> Result<(), String> {
-    let meta_path = get_backup_meta_path(app);
-    let content = serde_json::to_string_pretty(meta).map_err(|e| e.to_string())?;
-    fs::write(meta_path, content).map_err(|e| e.to_string())?;
-    Ok(())
+/// 在单独的事务中将备份移入回收区，提交失败时把文件移回原位置
+fn trash_backup_committed<R: Runtime>(app: &AppHandle<R>, backup: &Path) -> Result<(PathBuf, Vec<String>), String> {
+    let mut conn = open_db(app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let (trash_path, projects) = trash_backup(app, &tx, backup)?;
+    if let Err(e) = tx.commit() {
+        let _ = move_file(&trash_path, backup);
+        return Err(e.to_string());
+    }
+    Ok((trash_path, projects))
+}
+
+/// 将给定内容写入备份目录并记录元数据，返回备份文件路径
+pub(crate) fn create_backup<R: Runtime>(
+    app: &AppHandle<R>,
+    source_path: &Path,
+    content: &[u8],
+    projects: Vec<String>,
+) -> Result<PathBuf, String> {
+    let file_name = source_path.file_name().ok_or("Invalid file name")?.to_string_lossy();
+    let backup_dir = get_backup_dir(app)?;
+    
+    if !backup_dir.exists() {
+        fs::create_dir_all(&backup_dir).map_err(|e| e.to_string())?;
+    }
+
+    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+    let backup_name = format!("{}_{}_{}", timestamp, "backup", file_name);
+    let backup_path = backup_dir.join(&backup_name);
+
+    // 开启加密时写入的是密文，校验和与大小都按实际文件计算
+    let stored = encrypt_backup_if_enabled(app, content)?;
+    fs::write(&backup_path, &stored).map_err(|e| e.to_string())?;
+
+    // 记录项目名、校验和、书签数量等信息
+    let (machine_id, host) = machine_identity(app)?;
+    let record = BackupRecord {
+        source_path: Some(source_path.to_string_lossy().to_string()),
+        ide_version: detect_ide_version(source_path),
+        size: Some(stored.len() as i64),
+        sha256: Some(sha256_hex(&stored)),
+        bookmark_count: Some(parse_backup_content(&String::from_utf8_lossy(content)).len()),
+        projects,
+        created_at: Utc::now().to_rfc3339(),
+        origin_machine: Some(machine_id),
+        origin_host: Some(host),
+        ..BackupRecord::from_file_name(&backup_name)
+    };
+    let conn = open_db(app)?;
+    if let Err(e) = save_record(&conn, &record) {
+        // 元数据写入失败时不保留没有记录的备份
+        let _ = fs::remove_file(&backup_path);
+        return Err(e);
+    }
+    // 旁路元数据随备份文件一起同步，供其它机器识别
+    if let Err(e) = write_sidecar(app, &backup_dir, &record) {
+        println!("Failed to write backup sidecar: {}", e);
+    }
+
+    Ok(backup_path)
 }
 
 #[tauri::command]
@@ -158,36 +350,42 @@ pub fn backup_bookmark_file<R: Runtime>(
     if !source_path.exists() {
         return Err("Source file does not exist".to_string());
     }
-    
-    let file_name = source_path.file_name().ok_or("Invalid file name")?.to_string_lossy();
-    let backup_dir = get_backup_dir(&app);
-    
-    if !backup_dir.exists() {
-        fs::create_dir_all(&backup_dir).map_err(|e| e.to_string())?;
-    }
 
-    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-    let backup_name = format!("{}_{}_{}", timestamp, "backup", file_name);
-    let backup_path = backup_dir.join(&backup_name);
+    let content = fs::read(source_path).map_err(|e| e.to_string())?;
+    let backup_path = create_backup(&app, source_path, &content, projects)?;
 
-    fs::copy(source_path, &backup_path).map_err(|e| e.to_string())?;
+    Ok(backup_path.to_string_lossy().to_string())
+}
 
-    // 保存项目名元数据
-    let mut meta = load_backup_meta(&app);
-    meta.entries.insert(backup_name.clone(), projects);
-    save_backup_meta(&app, &meta)?;
+/// 解析备份文件名 YYYYMMDD_HHMMSS_backup_filename.xml，返回 (原文件名, 显示用时间)
+pub(crate) fn parse_backup_name(name: &str) -> Option<(String, String)> {
+    let parts: Vec<&str> = name.splitn(4, '_').collect();
+    if parts.len() < 4 || parts[2] != "backup" {
+        return None;
+    }
 
-    Ok(backup_path.to_string_lossy().to_string())
+    // Format timestamp for display
+    let display_time = if parts[0].len() >= 8 && parts[1].len() >= 6 {
+        format!("{}-{}-{} {}:{}:{}", 
+            &parts[0][0..4], &parts[0][4..6], &parts[0][6..8],
+            &parts[1][0..2], &parts[1][2..4], &parts[1][4..6]
+        )
+    } else {
+        format!("{}_{}", parts[0], parts[1])
+    };
+    Some((parts[3].to_string(), display_time))
 }
 
+/// 获取备份列表，可按标签、项目、时间范围筛选
 #[tauri::command]
-pub fn get_backup_list<R: Runtime>(app: AppHandle<R>) -> Result<Vec<BackupFile>, String> {
-    let backup_dir = get_backup_dir(&app);
+pub fn get_backup_list<R: Runtime>(app: AppHandle<R>, filter: Option<BackupFilter>) -> Result<Vec<BackupFile>, String> {
+    let backup_dir = get_backup_dir(&app)?;
     if !backup_dir.exists() {
         return Ok(Vec::new());
     }
 
-    let meta = load_backup_meta(&app);
+    let records = load_records(&open_db(&app)?, false)?;
+    let (machine_id, _) = machine_identity(&app)?;
     let entries = fs::read_dir(backup_dir).map_err(|e| e.to_string())?;
     let mut backups = Vec::new();
 
@@ -196,35 +394,35 @@ pub fn get_backup_list<R: Runtime>(app: AppHandle<R>) -> Result<Vec<BackupFile>,
         let p = entry.path();
         if p.is_file() {
             let name = p.file_name().unwrap_or_default().to_string_lossy().to_string();
-            // Expected format: YYYYMMDD_HHMMSS_backup_filename.xml
-            let parts: Vec<&str> = name.splitn(4, '_').collect();
-            if parts.len() >= 4 && parts[2] == "backup" {
-                let original_name = parts[3];
-                
-                // Format timestamp for display
-                let display_time = if parts[0].len() >= 8 && parts[1].len() >= 6 {
-                    format!("{}-{}-{} {}:{}:{}", 
-                        &parts[0][0..4], &parts[0][4..6], &parts[0][6..8],
-                        &parts[1][0..2], &parts[1][2..4], &parts[1][4..6]
-                    )
-                } else {
-                    format!("{}_{}", parts[0], parts[1])
-                };
-
-                // 获取项目名列表
-                let projects = meta.entries.get(&name).cloned().unwrap_or_default();
+            if let Some((original_name, display_time)) = parse_backup_name(&name) {
+                // 获取项目名列表、标签等信息
+                let info = records.get(&name).cloned().unwrap_or_default();
 
                 backups.push(BackupFile {
                     id: name.clone(),
-                    original_file_name: original_name.to_string(),
+                    original_file_name: original_name,
                     timestamp: display_time,
                     path: p.to_string_lossy().to_string(),
-                    projects,
+                    projects: info.projects,
+                    label: info.label,
+                    note: info.note,
+                    pinned: info.pinned,
+                    bookmark_count: info.bookmark_count,
+                    size: info.size,
+                    sha256: info.sha256,
+                    ide_version: info.ide_version,
+                    source_path: info.source_path,
+                    foreign: info.origin_machine.as_ref().is_some_and(|m| *m != machine_id),
+                    origin_host: info.origin_host,
                 });
             }
         }
     }
     
+    if let Some(filter) = filter {
+        backups.retain(|b| filter.matches(b));
+    }
+
     // Sort newest first
     backups.sort_by(|a, b| b.id.cmp(&a.id));
     
@@ -279,7 +477,7 @@ pub fn check_idea_running() -> Result<bool, String> {
 }
 
 #[tauri::command]
-pub fn restore_bookmark_file(backup_path: String, target_path: String) -> Result<(), String> {
+pub fn restore_bookmark_file<R: Runtime>(app: AppHandle<R>, backup_path: String, target_path: String) -> Result<(), String> {
     let backup = Path::new(&backup_path);
     let target = Path::new(&target_path);
 
@@ -287,15 +485,14 @@ pub fn restore_bookmark_file(backup_path: String, target_path: String) -> Result
         return Err("Backup file not found".to_string());
     }
 
-    // Ensure target directory exists (it should, but just in case)
-    if let Some(parent) = target.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
-
-    fs::copy(backup, target).map_err(|e| e.to_string())?;
+    let content = read_backup_content(backup)?;
+    let op = journal::write_file(target, &content)?;
+    let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+    journal::record(&app, &format!("从备份恢复 {}", file_name), op);
     Ok(())
 }
 
+/// 删除备份：移入回收区并记录到操作历史，可以撤销
 #[tauri::command]
 pub fn delete_backup_file<R: Runtime>(app: AppHandle<R>, backup_path: String) -> Result<(), String> {
     let backup = Path::new(&backup_path);
@@ -304,21 +501,265 @@ pub fn delete_backup_file<R: Runtime>(app: AppHandle<R>, backup_path: String) ->
         return Err("Backup file not found".to_string());
     }
     
-    // 获取文件名用于清理元数据
-    let file_name = backup.file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_default();
-    
-    fs::remove_file(backup).map_err(|e| e.to_string())?;
-    
-    // 清理元数据
-    let mut meta = load_backup_meta(&app);
-    meta.entries.remove(&file_name);
-    let _ = save_backup_meta(&app, &meta);
+    let (trash_path, projects) = trash_backup_committed(&app, backup)?;
+    let file_name = backup.file_name().unwrap_or_default().to_string_lossy();
+    journal::record(
+        &app,
+        &format!("删除备份 {}", file_name),
+        Operation::BackupDelete {
+            backup_path: backup_path.clone(),
+            trash_path: trash_path.to_string_lossy().to_string(),
+            projects,
+        },
+    );
     
     Ok(())
 }
 
+/// 修改备份的元数据，备份不存在时返回错误
+/// 备份 id 即备份文件名，拒绝可能指向备份目录之外的 id
+fn check_backup_id(id: &str) -> Result<(), String> {
+    if id.is_empty() || id.contains(['/', '\\']) || id.contains("..") {
+        return Err("Invalid backup id".to_string());
+    }
+    Ok(())
+}
+
+fn update_backup_entry<R: Runtime>(
+    app: &AppHandle<R>,
+    id: &str,
+    summary: &str,
+    update: impl FnOnce(&mut BackupRecord),
+) -> Result<(), String> {
+    check_backup_id(id)?;
+    if !get_backup_dir(app)?.join(id).exists() {
+        return Err("Backup file not found".to_string());
+    }
+    let mut conn = open_db(app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let before = snapshot_rows(&tx, "backups", "id = ?1", &[&id])?;
+    update_record(&tx, id, update)?;
+    let after = snapshot_rows(&tx, "backups", "id = ?1", &[&id])?;
+    if let Some(op) = table_rows("backups", &["id"], before, after) {
+        record_operation(&tx, &format!("{}：{}", summary, id), &op)?;
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// 设置备份标签，传空则清除
+#[tauri::command]
+pub fn set_backup_label<R: Runtime>(app: AppHandle<R>, id: String, label: Option<String>) -> Result<(), String> {
+    let label = label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+    update_backup_entry(&app, &id, "修改备份标签", |e| e.label = label)
+}
+
+/// 设置备份备注，传空则清除
+#[tauri::command]
+pub fn set_backup_note<R: Runtime>(app: AppHandle<R>, id: String, note: Option<String>) -> Result<(), String> {
+    let note = note.filter(|n| !n.trim().is_empty());
+    update_backup_entry(&app, &id, "修改备份备注", |e| e.note = note)
+}
+
+/// 置顶或取消置顶备份
+#[tauri::command]
+pub fn set_backup_pinned<R: Runtime>(app: AppHandle<R>, id: String, pinned: bool) -> Result<(), String> {
+    let summary = if pinned { "置顶备份" } else { "取消置顶备份" };
+    update_backup_entry(&app, &id, summary, |e| e.pinned = pinned)
+}
+
+/// 按保留策略清理备份：每个原文件路径只保留最近 keep_latest 个，且删除早于 older_than_days 天的备份
+/// 只清理本机创建的备份，其它机器写入同步目录的备份由各自的机器清理
+/// 置顶的备份不受影响也不计入数量；清理的备份移入回收区并可撤销，返回被清理的备份 ID
+#[tauri::command]
+pub fn prune_backups<R: Runtime>(
+    app: AppHandle<R>,
+    keep_latest: Option<usize>,
+    older_than_days: Option<u32>,
+) -> Result<Vec<String>, String> {
+    if keep_latest.is_none() && older_than_days.is_none() {
+        return Err("No retention rule given".to_string());
+    }
+    let cutoff = older_than_days
+        .map(|days| (Local::now() - Duration::days(days as i64)).format("%Y%m%d_%H%M%S").to_string());
+
+    let records = load_records(&open_db(&app)?, false)?;
+    let (machine_id, _) = machine_identity(&app)?;
+
+    // 列表已按时间从新到旧排序
+    let mut kept: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    let mut to_prune = Vec::new();
+    for b in get_backup_list(app.clone(), None)? {
+        let local = records
+            .get(&b.id)
+            .and_then(|r| r.origin_machine.as_ref())
+            .is_some_and(|m| *m == machine_id);
+        if b.pinned || !local {
+            continue;
+        }
+        let Some(source_path) = b.source_path.clone() else {
+            continue;
+        };
+        let count = kept.entry(source_path).or_insert(0);
+        let over_count = keep_latest.is_some_and(|keep| *count >= keep);
+        let too_old = cutoff.as_deref().is_some_and(|c| b.id.as_str() < c);
+        if over_count || too_old {
+            to_prune.push(b);
+        } else {
+            *count += 1;
+        }
+    }
+
+    let mut operations = Vec::new();
+    let mut pruned = Vec::new();
+    for b in to_prune {
+        let (trash_path, projects) = trash_backup_committed(&app, Path::new(&b.path))?;
+        operations.push(Operation::BackupDelete {
+            backup_path: b.path,
+            trash_path: trash_path.to_string_lossy().to_string(),
+            projects,
+        });
+        pruned.push(b.id);
+    }
+    if !operations.is_empty() {
+        journal::record(&app, &format!("清理 {} 个备份", pruned.len()), Operation::Batch { operations });
+    }
+    println!("Pruned {} backups", pruned.len());
+    Ok(pruned)
+}
+
+/// 清理回收区中超过保留天数的备份，返回清理的数量
+pub(crate) fn purge_expired_trash<R: Runtime>(app: &AppHandle<R>) -> Result<usize, String> {
+    let days = load_prefs(app)?.active().backup.trash_retention_days;
+    if days == 0 {
+        return Ok(0);
+    }
+    let cutoff = Utc::now() - Duration::days(days as i64);
+
+    let mut conn = open_db(app)?;
+    let records = load_records(&conn, true)?;
+    let expired: Vec<&BackupRecord> = records
+        .values()
+        .filter(|r| {
+            r.deleted_at
+                .as_deref()
+                .is_some_and(|d| DateTime::parse_from_rfc3339(d).is_ok_and(|d| d < cutoff))
+        })
+        .collect();
+    if expired.is_empty() {
+        return Ok(0);
+    }
+
+    // 先提交记录的删除，再删除文件；文件删除失败的记录写回，留待下次清理
+    let committed = conn.transaction().and_then(|tx| {
+        for r in &expired {
+            if let Err(e) = delete_record(&tx, &r.id) {
+                println!("Failed to delete trash record {}: {}", r.id, e);
+            }
+        }
+        tx.commit()
+    });
+    committed.map_err(|e| e.to_string())?;
+
+    let trash_dir = get_backup_trash_dir(app)?;
+    let mut purged = 0;
+    for r in expired {
+        let path = trash_dir.join(&r.id);
+        match fs::remove_file(&path) {
+            Ok(()) => purged += 1,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => purged += 1,
+            Err(e) => {
+                println!("Failed to remove expired backup {}: {}", path.display(), e);
+                if let Err(e) = save_record(&conn, r) {
+                    println!("Failed to keep trash record {}: {}", r.id, e);
+                }
+            }
+        }
+    }
+    println!("Purged {} expired backups from trash", purged);
+    Ok(purged)
+}
+
+/// 列出回收区中的备份，先清理已过期的备份
+#[tauri::command]
+pub fn list_trashed_backups<R: Runtime>(app: AppHandle<R>) -> Result<Vec<TrashedBackup>, String> {
+    purge_expired_trash(&app)?;
+
+    let trash_dir = get_backup_trash_dir(&app)?;
+    if !trash_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let records = load_records(&open_db(&app)?, true)?;
+    let mut trashed = Vec::new();
+    for entry in fs::read_dir(&trash_dir).map_err(|e| e.to_string())?.flatten() {
+        let p = entry.path();
+        if !p.is_file() {
+            continue;
+        }
+        let name = p.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let Some((original_name, display_time)) = parse_backup_name(&name) else {
+            continue;
+        };
+        let info = records.get(&name);
+
+        trashed.push(TrashedBackup {
+            id: name.clone(),
+            original_file_name: original_name,
+            timestamp: display_time,
+            deleted_at: info.and_then(|r| r.deleted_at.clone()).unwrap_or_default(),
+            path: p.to_string_lossy().to_string(),
+            projects: info.map(|r| r.projects.clone()).unwrap_or_default(),
+        });
+    }
+
+    // 最近删除的在前
+    trashed.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    Ok(trashed)
+}
+
+/// 将回收区中的备份恢复到备份列表，返回恢复后的路径
+#[tauri::command]
+pub fn restore_trashed_backup<R: Runtime>(app: AppHandle<R>, id: String) -> Result<String, String> {
+    check_backup_id(&id)?;
+    let trash_path = get_backup_trash_dir(&app)?.join(&id);
+    let backup_path = get_backup_dir(&app)?.join(&id);
+    if backup_path.exists() {
+        return Err("A backup with the same name already exists".to_string());
+    }
+
+    let mut conn = open_db(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let projects = get_record(&tx, &id)?
+        .map(|r| r.projects)
+        .unwrap_or_default();
+    restore_backup_from_trash(&tx, &trash_path, &backup_path, projects)?;
+    if let Err(e) = tx.commit() {
+        let _ = move_file(&backup_path, &trash_path);
+        return Err(e.to_string());
+    }
+    Ok(backup_path.to_string_lossy().to_string())
+}
+
+/// 清空回收区，返回永久删除的备份数量
+#[tauri::command]
+pub fn empty_backup_trash<R: Runtime>(app: AppHandle<R>) -> Result<usize, String> {
+    let trash_dir = get_backup_trash_dir(&app)?;
+    let mut removed = 0;
+    if trash_dir.exists() {
+        for entry in fs::read_dir(&trash_dir).map_err(|e| e.to_string())?.flatten() {
+            if entry.path().is_file() {
+                fs::remove_file(entry.path()).map_err(|e| e.to_string())?;
+                removed += 1;
+            }
+        }
+    }
+
+    let conn = open_db(&app)?;
+    conn.execute("DELETE FROM backups WHERE deleted_at IS NOT NULL", [])
+        .map_err(|e| e.to_string())?;
+    Ok(removed)
+}
+
 /// 读取备份文件中的书签内容
 #[tauri::command]
 pub fn read_backup_bookmarks(backup_path: String) -> Result<Vec<ParsedBookmark>, String> {
@@ -327,13 +768,13 @@ pub fn read_backup_bookmarks(backup_path: String) -> Result<Vec<ParsedBookmark>,
         return Err("Backup file not found".to_string());
     }
     
-    // 备份文件就是原始 XML 文件的副本，直接使用现有解析函数
-    let bookmarks = parse_bookmarks_from_global_workspace(path);
-    Ok(bookmarks)
+    // 备份文件是原始 XML 文件的副本（可能已加密），解密后使用现有解析函数
+    let content = read_backup_content(path)?;
+    Ok(parse_global_workspace_content(&content))
 }
 
 /// 辅助函数：提取 XML 属性值
-fn extract_attr_value(chunk: &str, attr: &str) -> Option<String> {
+pub(crate) fn extract_attr_value(chunk: &str, attr: &str) -> Option<String> {
     let patterns = [format!("{}=\"", attr), format!("{}='", attr)];
     for p in patterns {
         if let Some(idx) = chunk.find(&p) {
@@ -347,14 +788,51 @@ fn extract_attr_value(chunk: &str, attr: &str) -> Option<String> {
     None
 }
 
+/// 辅助函数：定位 XML 属性值在文本中的字节范围 (start, end)，用于原地改写
+pub(crate) fn attr_value_range(chunk: &str, attr: &str) -> Option<(usize, usize)> {
+    let patterns = [format!("{}=\"", attr), format!("{}='", attr)];
+    for p in patterns {
+        if let Some(idx) = chunk.find(&p) {
+            let start = idx + p.len();
+            let quote = p.chars().last().unwrap();
+            if let Some(end) = chunk[start..].find(quote) {
+                return Some((start, start + end));
+            }
+        }
+    }
+    None
+}
+
+/// 未提供项目根目录时，$PROJECT_DIR$ 在展示路径中的占位文本
+pub(crate) const PROJECT_DIR_PLACEHOLDER: &str = "[项目根目录]";
+
+/// 将 IDEA 的书签类型（DIGIT_1、LETTER_A）转换为助记符
+pub(crate) fn bookmark_type_to_mnemonic(bookmark_type: &str) -> Option<String> {
+    bookmark_type
+        .strip_prefix("DIGIT_")
+        .or_else(|| bookmark_type.strip_prefix("LETTER_"))
+        .filter(|m| m.len() == 1)
+        .map(|m| m.to_string())
+}
+
+/// 将助记符转换为 IDEA 的书签类型，非法助记符返回 None
+pub(crate) fn mnemonic_to_bookmark_type(mnemonic: &str) -> Option<String> {
+    let mut chars = mnemonic.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_digit() => Some(format!("DIGIT_{}", c)),
+        (Some(c), None) if c.is_ascii_alphabetic() => Some(format!("LETTER_{}", c.to_ascii_uppercase())),
+        _ => None,
+    }
+}
+
 /// 辅助函数：处理路径，将 $PROJECT_DIR$ 替换为实际路径
-fn clean_bookmark_path(raw_url: &str, project_root: Option<&str>) -> String {
+pub(crate) fn clean_bookmark_path(raw_url: &str, project_root: Option<&str>) -> String {
     let mut p = raw_url.to_string();
     if p.contains("$PROJECT_DIR$") {
         if let Some(root) = project_root {
             p = p.replace("file://$PROJECT_DIR$", root);
         } else {
-            p = p.replace("file://$PROJECT_DIR$", "[项目根目录]");
+            p = p.replace("file://$PROJECT_DIR$", PROJECT_DIR_PLACEHOLDER);
         }
     } else {
         p = p.replace("file://", "");
@@ -368,23 +846,26 @@ fn clean_bookmark_path(raw_url: &str, project_root: Option<&str>) -> String {
 
 /// 从全局 workspace XML 文件解析书签 (IDEA 2025+ 新格式)
 /// 这些文件位于 AppData/Roaming/JetBrains/IntelliJIdea20XX.X/workspace/*.xml
-fn parse_bookmarks_from_global_workspace(file_path: &Path) -> Vec<ParsedBookmark> {
-    let mut bookmarks = Vec::new();
-    
-    let content = match fs::read_to_string(file_path) {
-        Ok(c) => c,
+pub(crate) fn parse_bookmarks_from_global_workspace(file_path: &Path) -> Vec<ParsedBookmark> {
+    match fs::read_to_string(file_path) {
+        Ok(content) => parse_global_workspace_content(&content),
         Err(e) => {
             println!("  Failed to read file {:?}: {}", file_path, e);
-            return bookmarks;
+            Vec::new()
         }
-    };
+    }
+}
+
+/// 从 XML 文本解析 IDEA 2025+ 格式的书签（用于解密后的备份等不在磁盘上的内容）
+pub(crate) fn parse_global_workspace_content(content: &str) -> Vec<ParsedBookmark> {
+    let mut bookmarks = Vec::new();
 
     // 检查是否包含 BookmarksManager 组件
     if !content.contains("BookmarksManager") {
         return bookmarks;
     }
 
-    println!("  Found BookmarksManager in {:?}", file_path);
+    println!("  Found BookmarksManager");
 
     // 按 GroupState 分割，每个 GroupState 代表一个项目的书签组
     let group_chunks: Vec<&str> = content.split("<GroupState>").collect();
@@ -444,9 +925,12 @@ fn parse_bookmarks_from_global_workspace(file_path: &Path) -> Vec<ParsedBookmark
                     }
                 }
 
-                // 提取助记符
+                // 提取助记符 (entry key="mnemonic"，或 IDEA 原生的 <option name="type" value="DIGIT_1" />)
                 let mnemonic = if let Some(mn_idx) = bm_content.find("key=\"mnemonic\"") {
                     extract_attr_value(&bm_content[mn_idx..], "value").filter(|s| !s.is_empty())
+                } else if let Some(type_idx) = bm_content.find("name=\"type\"") {
+                    extract_attr_value(&bm_content[type_idx..], "value")
+                        .and_then(|t| bookmark_type_to_mnemonic(&t))
                 } else {
                     None
                 };
@@ -471,13 +955,16 @@ fn parse_bookmarks_from_global_workspace(file_path: &Path) -> Vec<ParsedBookmark
 }
 
 /// 从项目 .idea 目录的 XML 文件解析书签 (旧格式兼容)
-fn parse_bookmarks_from_project_idea(file_path: &Path, project_name: &str) -> Vec<ParsedBookmark> {
+pub(crate) fn parse_bookmarks_from_project_idea(file_path: &Path, project_name: &str) -> Vec<ParsedBookmark> {
+    match fs::read_to_string(file_path) {
+        Ok(content) => parse_project_idea_content(&content, project_name),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// 从 XML 文本解析旧版 .idea/workspace.xml 格式的书签
+pub(crate) fn parse_project_idea_content(content: &str, project_name: &str) -> Vec<ParsedBookmark> {
     let mut bookmarks = Vec::new();
-    
-    let content = match fs::read_to_string(file_path) {
-        Ok(c) => c,
-        Err(_) => return bookmarks,
-    };
 
     // 传统格式 (<bookmark ...>)
     if content.contains("<bookmark") {
@@ -523,7 +1010,7 @@ fn parse_bookmarks_from_project_idea(file_path: &Path, project_name: &str) -> Ve
 }
 
 /// 解析 recentProjects.xml 获取项目路径列表
-fn get_recent_projects(options_dir: &Path) -> Vec<PathBuf> {
+pub(crate) fn get_recent_projects(options_dir: &Path) -> Vec<PathBuf> {
     let mut projects = Vec::new();
     let recent_xml = options_dir.join("recentProjects.xml");
     
@@ -574,16 +1061,26 @@ fn get_recent_projects(options_dir: &Path) -> Vec<PathBuf> {
     projects
 }
 
-/// 从 workspace 目录读取所有书签（不保存到数据库，直接返回给前端展示）
-#[tauri::command]
-pub fn read_bookmarks_from_workspace(workspace_path: String) -> Result<Vec<ParsedBookmark>, String> {
-    let ws_path = Path::new(&workspace_path);
+/// 一个书签来源文件及从中解析出的书签
+pub(crate) struct BookmarkSource {
+    /// 书签所在的 XML 文件
+    pub file: PathBuf,
+    /// 旧格式来源所属项目的根目录（新格式无法从文件本身得知）
+    pub project_root: Option<PathBuf>,
+    /// 是否为项目 .idea 目录下的旧格式
+    pub legacy: bool,
+    pub bookmarks: Vec<ParsedBookmark>,
+}
+
+/// 按来源文件收集 workspace 对应的所有书签
+/// 优先读取全局 workspace 目录 (IDEA 2025+)，没有书签时回退到最近项目的 .idea 目录
+pub(crate) fn collect_bookmark_sources(ws_path: &Path) -> Result<Vec<BookmarkSource>, String> {
     let config_dir = ws_path.parent().ok_or("Cannot find config parent dir")?;
-    
+
     println!("Scanning bookmarks from config dir: {:?}", config_dir);
     println!("Workspace path: {:?}", ws_path);
 
-    let mut all_bookmarks = Vec::new();
+    let mut sources = Vec::new();
 
     // 方法1: 从全局 workspace 目录读取 (IDEA 2025+ 新格式)
     // 书签存储在 workspace/*.xml 文件中
@@ -596,20 +1093,29 @@ pub fn read_bookmarks_from_workspace(workspace_path: String) -> Result<Vec<Parse
                 if path.is_file() && path.extension().map_or(false, |ext| ext == "xml") {
                     println!("  Checking file: {:?}", path);
                     let bookmarks = parse_bookmarks_from_global_workspace(&path);
-                    all_bookmarks.extend(bookmarks);
+                    if !bookmarks.is_empty() {
+                        sources.push(BookmarkSource {
+                            file: path,
+                            project_root: None,
+                            legacy: false,
+                            bookmarks,
+                        });
+                    }
                 }
             }
         }
     }
 
     // 方法2: 如果全局 workspace 没找到书签，尝试从项目 .idea 目录读取 (旧版兼容)
-    if all_bookmarks.is_empty() {
+    if sources.is_empty() {
         println!("No bookmarks found in global workspace, trying project .idea directories...");
         
         let options_dir = config_dir.join("options");
         let project_paths = get_recent_projects(&options_dir);
         println!("Found {} recent projects", project_paths.len());
 
+        // 已收集书签的 (路径, 行号)，用于去重
+        let mut seen: Vec<(String, i32)> = Vec::new();
         for proj_path in &project_paths {
             let idea_dir = proj_path.join(".idea");
             let project_name = proj_path.file_name()
@@ -621,7 +1127,13 @@ pub fn read_bookmarks_from_workspace(workspace_path: String) -> Result<Vec<Parse
             if bm_xml.exists() {
                 let b = parse_bookmarks_from_project_idea(&bm_xml, &project_name);
                 println!("  Found {} bookmarks in {:?}", b.len(), bm_xml);
-                all_bookmarks.extend(b);
+                seen.extend(b.iter().map(|x| (x.file_path.clone(), x.line_number)));
+                sources.push(BookmarkSource {
+                    file: bm_xml,
+                    project_root: Some(proj_path.clone()),
+                    legacy: true,
+                    bookmarks: b,
+                });
             }
 
             // 检查 workspace.xml
@@ -630,18 +1142,33 @@ pub fn read_bookmarks_from_workspace(workspace_path: String) -> Result<Vec<Parse
                 let b = parse_bookmarks_from_project_idea(&ws_xml, &project_name);
                 println!("  Found {} bookmarks in {:?}", b.len(), ws_xml);
                 // 去重
-                for copy in b {
-                    let exists = all_bookmarks.iter().any(|existing| 
-                        existing.file_path == copy.file_path && existing.line_number == copy.line_number
-                    );
-                    if !exists {
-                        all_bookmarks.push(copy);
-                    }
-                }
+                let b: Vec<ParsedBookmark> = b
+                    .into_iter()
+                    .filter(|copy| !seen.contains(&(copy.file_path.clone(), copy.line_number)))
+                    .collect();
+                seen.extend(b.iter().map(|x| (x.file_path.clone(), x.line_number)));
+                sources.push(BookmarkSource {
+                    file: ws_xml,
+                    project_root: Some(proj_path.clone()),
+                    legacy: true,
+                    bookmarks: b,
+                });
             }
         }
     }
 
+    Ok(sources)
+}
+
+/// 从 workspace 目录读取所有书签（不保存到数据库，直接返回给前端展示）
+#[tauri::command]
+pub fn read_bookmarks_from_workspace(workspace_path: String) -> Result<Vec<ParsedBookmark>, String> {
+    let sources = collect_bookmark_sources(Path::new(&workspace_path))?;
+    let mut all_bookmarks: Vec<ParsedBookmark> = sources
+        .into_iter()
+        .flat_map(|source| source.bookmarks)
+        .collect();
+
     println!("Total bookmarks found: {}", all_bookmarks.len());
 
     // 按项目名称排序
@@ -0,0 +1,414 @@
+use std::fs;
+use std::path::Path;
+use chrono::{Local, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::{AppHandle, Runtime};
+
+use crate::annotations::BookmarkKey;
+use crate::db::open_db;
+use crate::journal::{record_operation, snapshot_rows, table_rows, Operation, RowSnapshot};
+use crate::exporter::{code_language, read_snippet, render_code_block};
+
+/// 导览文档中每一步代码片段上下各取的行数
+const WALKTHROUGH_SNIPPET_RADIUS: i32 = 4;
+
+/// 书签合集
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct Collection {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    pub item_count: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// 合集中的一步
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct CollectionItem {
+    pub id: i64,
+    /// 从 1 开始的顺序
+    pub position: i64,
+    pub project_name: String,
+    pub file_path: String,
+    pub line_number: i32,
+    /// 加入合集时书签的描述
+    pub description: String,
+    /// 该步骤的说明（Markdown）
+    pub step_note: String,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct CollectionDetail {
+    pub collection: Collection,
+    pub items: Vec<CollectionItem>,
+}
+
+fn load_collection(conn: &Connection, id: i64) -> Result<Collection, String> {
+    conn.query_row(
+        "SELECT c.id, c.name, c.description,
+                (SELECT COUNT(*) FROM collection_items i WHERE i.collection_id = c.id),
+                c.created_at, c.updated_at
+         FROM collections c WHERE c.id = ?1",
+        params![id],
+        |row| {
+            Ok(Collection {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                item_count: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        },
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn load_items(conn: &Connection, collection_id: i64) -> Result<Vec<CollectionItem>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, position, project_name, file_path, line_number, description, step_note
+             FROM collection_items WHERE collection_id = ?1 ORDER BY position",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![collection_id], |row| {
+            Ok(CollectionItem {
+                id: row.get(0)?,
+                position: row.get(1)?,
+                project_name: row.get(2)?,
+                file_path: row.get(3)?,
+                line_number: row.get(4)?,
+                description: row.get(5)?,
+                step_note: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut items = Vec::new();
+    for item in rows {
+        items.push(item.map_err(|e| e.to_string())?);
+    }
+    Ok(items)
+}
+
+fn touch_collection(conn: &Connection, collection_id: i64) -> Result<(), String> {
+    conn.execute(
+        "UPDATE collections SET updated_at = ?1 WHERE id = ?2",
+        params![Utc::now().to_rfc3339(), collection_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 按给定顺序重写步骤序号（1, 2, 3 ...）
+fn write_positions(conn: &Connection, item_ids: &[i64]) -> Result<(), String> {
+    for (i, item_id) in item_ids.iter().enumerate() {
+        conn.execute(
+            "UPDATE collection_items SET position = ?1 WHERE id = ?2",
+            params![(i + 1) as i64, item_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 书签行号变化后，同步迁移指向它的合集步骤，返回被迁移的步骤 ID
+pub(crate) fn move_collection_items(
+    conn: &Connection,
+    project_name: &str,
+    file_path: &str,
+    old_line: i32,
+    new_line: i32,
+) -> Result<Vec<i64>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id FROM collection_items WHERE project_name = ?1 AND file_path = ?2 AND line_number = ?3")
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map(params![project_name, file_path, old_line], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    set_collection_items_line(conn, &ids, new_line)?;
+    Ok(ids)
+}
+
+/// 将指定的合集步骤指向新的行号
+pub(crate) fn set_collection_items_line(conn: &Connection, item_ids: &[i64], line_number: i32) -> Result<(), String> {
+    for id in item_ids {
+        conn.execute(
+            "UPDATE collection_items SET line_number = ?1 WHERE id = ?2",
+            params![line_number, id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 合集及其全部步骤的快照
+fn snapshot_collection(conn: &Connection, collection_id: i64) -> Result<(Vec<RowSnapshot>, Vec<RowSnapshot>), String> {
+    Ok((
+        snapshot_rows(conn, "collections", "id = ?1", &[&collection_id])?,
+        snapshot_rows(conn, "collection_items", "collection_id = ?1 ORDER BY id", &[&collection_id])?,
+    ))
+}
+
+/// 在事务中修改合集，并把合集及其步骤修改前后的快照记录到操作历史
+fn change_collection<R: Runtime, T>(
+    app: &AppHandle<R>,
+    collection_id: i64,
+    summary: &str,
+    change: impl FnOnce(&Connection) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut conn = open_db(app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let (collection_before, items_before) = snapshot_collection(&tx, collection_id)?;
+    let name = collection_before
+        .first()
+        .and_then(|r| r.get("name"))
+        .and_then(|n| n.as_str())
+        .ok_or(format!("Collection {} not found", collection_id))?
+        .to_string();
+
+    let result = change(&tx)?;
+    let (collection_after, items_after) = snapshot_collection(&tx, collection_id)?;
+    // 删除合集时先删除步骤，撤销时逆序执行，合集先于步骤恢复
+    let deleted = collection_after.is_empty();
+    let collection_op = table_rows("collections", &["id"], collection_before, collection_after);
+    let items_op = table_rows("collection_items", &["id"], items_before, items_after);
+    let ordered = if deleted { [items_op, collection_op] } else { [collection_op, items_op] };
+    let operations: Vec<Operation> = ordered.into_iter().flatten().collect();
+    if !operations.is_empty() {
+        record_operation(&tx, &format!("{}：{}", summary, name), &Operation::Batch { operations })?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(result)
+}
+
+/// 步骤所属的合集
+fn item_collection(conn: &Connection, item_id: i64) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT collection_id FROM collection_items WHERE id = ?1",
+        params![item_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or(format!("Collection item {} not found", item_id))
+}
+
+/// 去掉首尾空白后的合集名称，不能为空
+fn validate_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Collection name cannot be empty".to_string());
+    }
+    Ok(name)
+}
+
+/// 创建书签合集
+#[tauri::command]
+pub fn create_collection<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    description: Option<String>,
+) -> Result<Collection, String> {
+    let name = validate_name(&name)?;
+    let conn = open_db(&app)?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO collections (name, description, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+        params![name, description.unwrap_or_default(), now],
+    )
+    .map_err(|e| e.to_string())?;
+    load_collection(&conn, conn.last_insert_rowid())
+}
+
+/// 列出所有书签合集
+#[tauri::command]
+pub fn list_collections<R: Runtime>(app: AppHandle<R>) -> Result<Vec<Collection>, String> {
+    let conn = open_db(&app)?;
+    let mut stmt = conn
+        .prepare("SELECT id FROM collections ORDER BY updated_at DESC")
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    ids.into_iter().map(|id| load_collection(&conn, id)).collect()
+}
+
+/// 获取合集及其全部步骤
+#[tauri::command]
+pub fn get_collection<R: Runtime>(app: AppHandle<R>, id: i64) -> Result<CollectionDetail, String> {
+    let conn = open_db(&app)?;
+    Ok(CollectionDetail {
+        collection: load_collection(&conn, id)?,
+        items: load_items(&conn, id)?,
+    })
+}
+
+/// 修改合集名称和说明
+#[tauri::command]
+pub fn update_collection<R: Runtime>(
+    app: AppHandle<R>,
+    id: i64,
+    name: String,
+    description: String,
+) -> Result<(), String> {
+    let name = validate_name(&name)?;
+    change_collection(&app, id, "修改合集", |conn| {
+        conn.execute(
+            "UPDATE collections SET name = ?1, description = ?2, updated_at = ?3 WHERE id = ?4",
+            params![name, description, Utc::now().to_rfc3339(), id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    })
+}
+
+/// 删除合集（步骤随之删除，书签本身不受影响）
+#[tauri::command]
+pub fn delete_collection<R: Runtime>(app: AppHandle<R>, id: i64) -> Result<(), String> {
+    change_collection(&app, id, "删除合集", |conn| {
+        conn.execute("DELETE FROM collections WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    })
+}
+
+/// 向合集末尾添加一个书签步骤，返回步骤 ID
+#[tauri::command]
+pub fn add_collection_item<R: Runtime>(
+    app: AppHandle<R>,
+    collection_id: i64,
+    key: BookmarkKey,
+    description: Option<String>,
+    step_note: Option<String>,
+) -> Result<i64, String> {
+    change_collection(&app, collection_id, "添加合集步骤", |conn| {
+        let next_position: i64 = conn
+            .query_row(
+                "SELECT COALESCE(MAX(position), 0) + 1 FROM collection_items WHERE collection_id = ?1",
+                params![collection_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+
+        conn.execute(
+            "INSERT INTO collection_items (collection_id, position, project_name, file_path, line_number, description, step_note)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                collection_id,
+                next_position,
+                key.project_name,
+                key.file_path,
+                key.line_number,
+                description.unwrap_or_default(),
+                step_note.unwrap_or_default()
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        let item_id = conn.last_insert_rowid();
+        touch_collection(conn, collection_id)?;
+        Ok(item_id)
+    })
+}
+
+/// 从合集中移除一个步骤，并重新编号
+#[tauri::command]
+pub fn remove_collection_item<R: Runtime>(app: AppHandle<R>, item_id: i64) -> Result<(), String> {
+    let collection_id = item_collection(&open_db(&app)?, item_id)?;
+    change_collection(&app, collection_id, "移除合集步骤", |conn| {
+        conn.execute("DELETE FROM collection_items WHERE id = ?1", params![item_id])
+            .map_err(|e| e.to_string())?;
+        let remaining: Vec<i64> = load_items(conn, collection_id)?.iter().map(|i| i.id).collect();
+        write_positions(conn, &remaining)?;
+        touch_collection(conn, collection_id)
+    })
+}
+
+/// 修改步骤说明
+#[tauri::command]
+pub fn update_collection_step_note<R: Runtime>(app: AppHandle<R>, item_id: i64, step_note: String) -> Result<(), String> {
+    let collection_id = item_collection(&open_db(&app)?, item_id)?;
+    change_collection(&app, collection_id, "修改步骤说明", |conn| {
+        conn.execute(
+            "UPDATE collection_items SET step_note = ?1 WHERE id = ?2",
+            params![step_note, item_id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    })
+}
+
+/// 按给定的步骤 ID 顺序重新排列合集，必须包含合集中的全部步骤
+#[tauri::command]
+pub fn reorder_collection_items<R: Runtime>(
+    app: AppHandle<R>,
+    collection_id: i64,
+    item_ids: Vec<i64>,
+) -> Result<(), String> {
+    change_collection(&app, collection_id, "调整合集步骤顺序", |conn| {
+        let mut current: Vec<i64> = load_items(conn, collection_id)?.iter().map(|i| i.id).collect();
+        let mut requested = item_ids.clone();
+        current.sort_unstable();
+        requested.sort_unstable();
+        if current != requested {
+            return Err("Item list does not match the collection".to_string());
+        }
+
+        write_positions(conn, &item_ids)?;
+        touch_collection(conn, collection_id)
+    })
+}
+
+/// 将合集导出为 Markdown 导览文档，每一步附带代码片段
+#[tauri::command]
+pub fn export_collection_walkthrough<R: Runtime>(
+    app: AppHandle<R>,
+    collection_id: i64,
+    output_path: String,
+) -> Result<(), String> {
+    let conn = open_db(&app)?;
+    let collection = load_collection(&conn, collection_id)?;
+    let items = load_items(&conn, collection_id)?;
+
+    let mut out = format!("# {}\n\n", collection.name);
+    if !collection.description.trim().is_empty() {
+        out.push_str(&format!("{}\n\n", collection.description.trim()));
+    }
+    out.push_str(&format!(
+        "> 共 {} 步，导出时间：{}\n\n",
+        items.len(),
+        Local::now().format("%Y-%m-%d %H:%M:%S")
+    ));
+
+    for item in &items {
+        let file_name = Path::new(&item.file_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let title = if item.description.is_empty() { &file_name } else { &item.description };
+
+        out.push_str(&format!("## 第 {} 步：{}\n\n", item.position, title));
+        out.push_str(&format!(
+            "- 项目：{}\n- 位置：`{}:{}`\n\n",
+            item.project_name, item.file_path, item.line_number
+        ));
+        if !item.step_note.trim().is_empty() {
+            out.push_str(&format!("{}\n\n", item.step_note.trim()));
+        }
+        if let Some(snippet) = read_snippet(&item.file_path, item.line_number, WALKTHROUGH_SNIPPET_RADIUS) {
+            out.push_str(&render_code_block(&snippet, code_language(&file_name)));
+        }
+    }
+
+    if let Some(parent) = Path::new(&output_path).parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(&output_path, out).map_err(|e| e.to_string())
+}
@@ -0,0 +1,363 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{Local, Utc};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::backup_integrity::sha256_hex;
+use crate::bookmark_manager::check_idea_running;
+use crate::journal::{self, Operation};
+
+/// 默认快照的配置内容（相对于 IDE 配置目录）
+const DEFAULT_INCLUDES: &[&str] = &["options/*.xml", "keymaps/**", "codestyles/**", "templates/**", "workspace/**"];
+const MANIFEST_FILE: &str = "manifest.json";
+const FILES_DIR: &str = "files";
+
+/// 快照中的一个文件
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct SnapshotFile {
+    /// 相对于配置目录的路径，使用 / 分隔
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// IDE 配置快照
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct ConfigSnapshot {
+    pub id: String,
+    /// 来源 IDE 版本目录名，如 IntelliJIdea2025.1
+    pub version_name: String,
+    pub source_path: String,
+    pub label: Option<String>,
+    pub created_at: String,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub files: Vec<SnapshotFile>,
+}
+
+/// 恢复配置快照的结果
+#[derive(serde::Serialize, Debug)]
+pub struct ConfigRestoreReport {
+    pub target_path: String,
+    pub restored: Vec<String>,
+    /// 内容与快照相同、无需写入的文件数量
+    pub unchanged: usize,
+}
+
+fn get_snapshot_root<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(app_dir.join("config_snapshots"))
+}
+
+fn snapshot_dir<R: Runtime>(app: &AppHandle<R>, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || id.contains(['/', '\\']) || id.contains("..") {
+        return Err("Invalid snapshot id".to_string());
+    }
+    Ok(get_snapshot_root(app)?.join(id))
+}
+
+/// 匹配单个路径段，支持 * 与 ?
+fn match_segment(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((star_pi, star_ti)) = star {
+            // 回溯：让上一个 * 多匹配一个字符
+            pi = star_pi + 1;
+            ti = star_ti + 1;
+            star = Some((star_pi, star_ti + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|i| match_segments(rest, &path[i..])),
+        Some((seg, rest)) => {
+            !path.is_empty() && match_segment(seg, path[0]) && match_segments(rest, &path[1..])
+        }
+    }
+}
+
+/// 目录下是否可能有文件匹配该模式，用于跳过无关目录（如 plugins）
+fn match_dir_prefix(pattern: &[&str], dir: &[&str]) -> bool {
+    if dir.is_empty() {
+        return true;
+    }
+    match pattern.split_first() {
+        None => false,
+        Some((&"**", _)) => true,
+        Some((seg, rest)) => match_segment(seg, dir[0]) && match_dir_prefix(rest, &dir[1..]),
+    }
+}
+
+/// 拆分模式为路径段；以 / 结尾的模式表示整个目录
+fn pattern_segments(pattern: &str) -> Vec<&str> {
+    let pattern = pattern.trim().trim_start_matches("./").trim_start_matches('/');
+    let mut segments: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    if pattern.ends_with('/') {
+        segments.push("**");
+    }
+    segments
+}
+
+/// 路径匹配 include/exclude 模式：* 与 ? 不跨越路径段，** 匹配任意层目录
+struct PathFilter<'a> {
+    include: Vec<Vec<&'a str>>,
+    exclude: Vec<Vec<&'a str>>,
+}
+
+impl<'a> PathFilter<'a> {
+    fn new(include: &'a [String], exclude: &'a [String]) -> Self {
+        PathFilter {
+            include: include.iter().map(|p| pattern_segments(p)).filter(|p| !p.is_empty()).collect(),
+            exclude: exclude.iter().map(|p| pattern_segments(p)).filter(|p| !p.is_empty()).collect(),
+        }
+    }
+
+    fn matches(&self, rel_path: &str) -> bool {
+        let path: Vec<&str> = rel_path.split('/').collect();
+        self.include.iter().any(|p| match_segments(p, &path))
+            && !self.exclude.iter().any(|p| match_segments(p, &path))
+    }
+
+    fn may_contain(&self, rel_dir: &str) -> bool {
+        let dir: Vec<&str> = rel_dir.split('/').collect();
+        self.include.iter().any(|p| match_dir_prefix(p, &dir))
+    }
+}
+
+/// 递归收集配置目录中匹配的文件，返回相对路径
+fn collect_files(root: &Path, dir: &Path, filter: &PathFilter, out: &mut Vec<String>) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())?.flatten() {
+        let path = entry.path();
+        let Ok(rel) = path.strip_prefix(root) else {
+            continue;
+        };
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if filter.may_contain(&rel) {
+                collect_files(root, &path, filter, out)?;
+            }
+        } else if file_type.is_file() && filter.matches(&rel) {
+            out.push(rel);
+        }
+    }
+    Ok(())
+}
+
+fn resolve_patterns(include: Option<Vec<String>>, exclude: Option<Vec<String>>) -> (Vec<String>, Vec<String>) {
+    let include = include
+        .map(|p| p.into_iter().filter(|s| !s.trim().is_empty()).collect::<Vec<_>>())
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| DEFAULT_INCLUDES.iter().map(|s| s.to_string()).collect());
+    let exclude = exclude.unwrap_or_default().into_iter().filter(|s| !s.trim().is_empty()).collect();
+    (include, exclude)
+}
+
+fn list_config_files(version_path: &Path, include: &[String], exclude: &[String]) -> Result<Vec<String>, String> {
+    if !version_path.is_dir() {
+        return Err("IDE config directory not found".to_string());
+    }
+    let filter = PathFilter::new(include, exclude);
+    let mut files = Vec::new();
+    collect_files(version_path, version_path, &filter, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn load_manifest(dir: &Path) -> Result<ConfigSnapshot, String> {
+    let content = fs::read_to_string(dir.join(MANIFEST_FILE)).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+/// 预览快照将包含的文件（相对路径），include 为空时使用默认的配置范围
+#[tauri::command]
+pub fn preview_config_snapshot(
+    version_path: String,
+    include: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+) -> Result<Vec<String>, String> {
+    let (include, exclude) = resolve_patterns(include, exclude);
+    list_config_files(Path::new(&version_path), &include, &exclude)
+}
+
+/// 为 IDE 版本的配置目录创建快照（options/*.xml、keymaps、codestyles、templates、workspace 等）
+#[tauri::command]
+pub fn create_config_snapshot<R: Runtime>(
+    app: AppHandle<R>,
+    version_path: String,
+    include: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    label: Option<String>,
+) -> Result<ConfigSnapshot, String> {
+    let source = Path::new(&version_path);
+    let (include, exclude) = resolve_patterns(include, exclude);
+    let rel_paths = list_config_files(source, &include, &exclude)?;
+    if rel_paths.is_empty() {
+        return Err("没有匹配的配置文件".to_string());
+    }
+
+    let version_name = source.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let root = get_snapshot_root(&app)?;
+    fs::create_dir_all(&root).map_err(|e| e.to_string())?;
+    // id 带微秒，仍按时间排序；create_dir 在目录已存在时失败，重新取时间即可避免冲突
+    let (id, dir) = loop {
+        let id = format!("{}_{}", Local::now().format("%Y%m%d_%H%M%S_%6f"), version_name);
+        let dir = snapshot_dir(&app, &id)?;
+        match fs::create_dir(&dir) {
+            Ok(()) => break (id, dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.to_string()),
+        }
+    };
+
+    let mut snapshot = ConfigSnapshot {
+        id,
+        version_name,
+        source_path: version_path.clone(),
+        label: label.filter(|l| !l.trim().is_empty()),
+        created_at: Utc::now().to_rfc3339(),
+        include,
+        exclude,
+        files: Vec::new(),
+    };
+
+    let result = (|| -> Result<(), String> {
+        for rel in rel_paths {
+            // 按字节复制，非文本文件也原样保存
+            let content = fs::read(source.join(&rel)).map_err(|e| e.to_string())?;
+            let target = dir.join(FILES_DIR).join(&rel);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            fs::write(&target, &content).map_err(|e| e.to_string())?;
+            snapshot.files.push(SnapshotFile { path: rel, size: content.len() as u64, sha256: sha256_hex(&content) });
+        }
+        let json = serde_json::to_string_pretty(&snapshot).map_err(|e| e.to_string())?;
+        fs::write(dir.join(MANIFEST_FILE), json).map_err(|e| e.to_string())
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(e);
+    }
+
+    println!("Created config snapshot {} with {} files", snapshot.id, snapshot.files.len());
+    Ok(snapshot)
+}
+
+/// 列出所有配置快照，最新的在前
+#[tauri::command]
+pub fn list_config_snapshots<R: Runtime>(app: AppHandle<R>) -> Result<Vec<ConfigSnapshot>, String> {
+    let root = get_snapshot_root(&app)?;
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(&root).map_err(|e| e.to_string())?.flatten() {
+        if !entry.path().is_dir() {
+            continue;
+        }
+        match load_manifest(&entry.path()) {
+            Ok(snapshot) => snapshots.push(snapshot),
+            Err(e) => println!("Skipping config snapshot {:?}: {}", entry.path(), e),
+        }
+    }
+    snapshots.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(snapshots)
+}
+
+/// 将快照恢复到同一或另一个 IDE 版本的配置目录，可用 include/exclude 只恢复部分文件
+/// 写入前先校验全部快照文件，任何文件损坏都不会改动目标目录；恢复可以撤销
+#[tauri::command]
+pub fn restore_config_snapshot<R: Runtime>(
+    app: AppHandle<R>,
+    id: String,
+    target_path: String,
+    include: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+) -> Result<ConfigRestoreReport, String> {
+    if check_idea_running()? {
+        return Err("IntelliJ IDEA 正在运行，请先关闭 IDE 再恢复配置".to_string());
+    }
+    let dir = snapshot_dir(&app, &id)?;
+    let snapshot = load_manifest(&dir)?;
+    let target = Path::new(&target_path);
+    if !target.is_dir() {
+        return Err("Target IDE config directory not found".to_string());
+    }
+
+    let include = include.filter(|p| !p.is_empty()).unwrap_or_else(|| vec!["**".to_string()]);
+    let exclude = exclude.unwrap_or_default();
+    let filter = PathFilter::new(&include, &exclude);
+
+    let mut pending = Vec::new();
+    for file in snapshot.files.iter().filter(|f| filter.matches(&f.path)) {
+        let content = fs::read(dir.join(FILES_DIR).join(&file.path)).map_err(|e| e.to_string())?;
+        if sha256_hex(&content) != file.sha256 {
+            return Err(format!("快照文件已损坏：{}", file.path));
+        }
+        pending.push((file.path.clone(), content));
+    }
+
+    let mut report = ConfigRestoreReport { target_path: target_path.clone(), restored: Vec::new(), unchanged: 0 };
+    let mut operations = Vec::new();
+    for (rel, content) in pending {
+        let path = target.join(&rel);
+        if fs::read(&path).is_ok_and(|current| current == content) {
+            report.unchanged += 1;
+            continue;
+        }
+        // 操作历史中只记录内容的哈希，二进制文件同样可以撤销
+        match journal::write_file_bytes(&path, &content) {
+            Ok(op) => operations.push(op),
+            Err(e) => {
+                // 已写入的部分仍记入历史，方便撤销
+                if !operations.is_empty() {
+                    journal::record(&app, &format!("部分恢复配置快照 {}", snapshot.id), Operation::Batch { operations });
+                }
+                return Err(format!("{}: {}", rel, e));
+            }
+        }
+        report.restored.push(rel);
+    }
+
+    if !operations.is_empty() {
+        let target_name = target.file_name().unwrap_or_default().to_string_lossy();
+        journal::record(
+            &app,
+            &format!("恢复配置快照 {} 到 {}", snapshot.id, target_name),
+            Operation::Batch { operations },
+        );
+    }
+    println!("Restored {} config files to {:?}", report.restored.len(), target);
+    Ok(report)
+}
+
+/// 删除配置快照
+#[tauri::command]
+pub fn delete_config_snapshot<R: Runtime>(app: AppHandle<R>, id: String) -> Result<(), String> {
+    let dir = snapshot_dir(&app, &id)?;
+    if !dir.join(MANIFEST_FILE).exists() {
+        return Err("Config snapshot not found".to_string());
+    }
+    fs::remove_dir_all(dir).map_err(|e| e.to_string())
+}
@@ -1,10 +1,18 @@
-use rusqlite::{params, Connection, Result};
+use rusqlite::{params, params_from_iter, Connection, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::{AppHandle, Manager, Runtime};
 use chrono::Utc;
 
-#[derive(Serialize, Deserialize, Debug)]
+use crate::bookmark_manager::{
+    get_recent_projects, parse_bookmarks_from_global_workspace, parse_bookmarks_from_project_idea,
+    PROJECT_DIR_PLACEHOLDER,
+};
+use crate::journal::{record_operation, Operation};
+use crate::preferences::{load_prefs, Profile};
+use crate::project_detect::detect_project_name;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Bookmark {
     pub id: Option<i64>,
     pub title: String,
@@ -13,60 +21,264 @@ pub struct Bookmark {
     pub content: String,
     pub created_at: Option<String>,
     pub project: String,
+    /// 导入来源中的 IDEA 分组名
+    #[serde(default)]
+    pub group_name: Option<String>,
+    /// 导入来源中的助记符
+    #[serde(default)]
+    pub mnemonic: Option<String>,
+}
+
+/// 导入成功或因重复而跳过的书签
+#[derive(Serialize, Debug)]
+pub struct ImportEntry {
+    pub file_path: String,
+    pub line_number: i32,
+    pub group_name: String,
+}
+
+/// 导入失败的书签及原因
+#[derive(Serialize, Debug)]
+pub struct ImportFailure {
+    pub file_path: Option<String>,
+    pub line_number: Option<i32>,
+    pub reason: String,
 }
 
-fn get_db_path<R: Runtime>(app: &AppHandle<R>) -> PathBuf {
+/// 导入报告
+#[derive(Serialize, Debug, Default)]
+pub struct ImportReport {
+    pub imported: Vec<ImportEntry>,
+    pub skipped_duplicate: Vec<ImportEntry>,
+    pub failed: Vec<ImportFailure>,
+}
+
+fn get_db_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    Ok(profile_db_path(app, load_prefs(app)?.active()))
+}
+
+/// 档案使用的数据库文件：每个数据库命名空间一个，默认命名空间沿用 bookmarks.db
+pub(crate) fn profile_db_path<R: Runtime>(app: &AppHandle<R>, profile: &Profile) -> PathBuf {
     let app_dir = app.path().app_data_dir().expect("failed to get app data dir");
     if !app_dir.exists() {
         std::fs::create_dir_all(&app_dir).expect("failed to create app data dir");
     }
-    app_dir.join("bookmarks.db")
+    app_dir.join(format!("{}.db", profile.namespaced("bookmarks")))
+}
+
+/// 打开本地数据库连接，供各功能模块复用
+pub(crate) fn open_db<R: Runtime>(app: &AppHandle<R>) -> Result<Connection, String> {
+    let conn = Connection::open(get_db_path(app)?).map_err(|e| e.to_string())?;
+    conn.execute_batch("PRAGMA foreign_keys = ON").map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+/// 数据库结构迁移脚本，按顺序执行；第 N 条执行完后 user_version 记为 N
+const MIGRATIONS: &[&str] = &[
+    // v1: 本地书签、书签锚点与 Git 提交记录
+    "CREATE TABLE IF NOT EXISTS bookmarks (
+        id INTEGER PRIMARY KEY,
+        title TEXT NOT NULL,
+        file_path TEXT NOT NULL,
+        line_number INTEGER,
+        content TEXT,
+        created_at TEXT NOT NULL,
+        project TEXT DEFAULT 'Unknown'
+    );
+    -- 书签锚点：保存书签所在行及上下文，用于代码变动后重新定位
+    CREATE TABLE IF NOT EXISTS bookmark_anchors (
+        id INTEGER PRIMARY KEY,
+        file_path TEXT NOT NULL,
+        line_number INTEGER NOT NULL,
+        line_text TEXT NOT NULL,
+        context_before TEXT NOT NULL,
+        context_after TEXT NOT NULL,
+        captured_at TEXT NOT NULL,
+        UNIQUE(file_path, line_number)
+    );
+    -- 书签记录时所在的 Git 提交，用于将行号映射到 HEAD
+    CREATE TABLE IF NOT EXISTS bookmark_commits (
+        id INTEGER PRIMARY KEY,
+        file_path TEXT NOT NULL,
+        line_number INTEGER NOT NULL,
+        commit_hash TEXT NOT NULL,
+        captured_at TEXT NOT NULL,
+        UNIQUE(file_path, line_number)
+    );",
+    // v2: 保留导入书签的 IDEA 分组名与助记符
+    "ALTER TABLE bookmarks ADD COLUMN group_name TEXT;
+    ALTER TABLE bookmarks ADD COLUMN mnemonic TEXT;",
+    // v3: IDEA 书签的备注、颜色、置顶与标签，按 (项目, 路径, 行号) 关联，重新扫描后仍然有效
+    "CREATE TABLE IF NOT EXISTS bookmark_annotations (
+        id INTEGER PRIMARY KEY,
+        project_name TEXT NOT NULL,
+        file_path TEXT NOT NULL,
+        line_number INTEGER NOT NULL,
+        note TEXT NOT NULL DEFAULT '',
+        color TEXT,
+        pinned INTEGER NOT NULL DEFAULT 0,
+        favorite INTEGER NOT NULL DEFAULT 0,
+        updated_at TEXT NOT NULL,
+        UNIQUE(project_name, file_path, line_number)
+    );
+    CREATE TABLE IF NOT EXISTS tags (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE
+    );
+    CREATE TABLE IF NOT EXISTS bookmark_tags (
+        annotation_id INTEGER NOT NULL REFERENCES bookmark_annotations(id) ON DELETE CASCADE,
+        tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+        PRIMARY KEY (annotation_id, tag_id)
+    );",
+    // v4: 跨项目的书签合集（导览），每一步引用一个书签并附带说明
+    "CREATE TABLE IF NOT EXISTS collections (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        description TEXT NOT NULL DEFAULT '',
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS collection_items (
+        id INTEGER PRIMARY KEY,
+        collection_id INTEGER NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        project_name TEXT NOT NULL,
+        file_path TEXT NOT NULL,
+        line_number INTEGER NOT NULL,
+        description TEXT NOT NULL DEFAULT '',
+        step_note TEXT NOT NULL DEFAULT ''
+    );",
+    // v5: 操作历史，用于撤销/重做；payload 为序列化后的操作内容
+    "CREATE TABLE IF NOT EXISTS operation_journal (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        kind TEXT NOT NULL,
+        summary TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        undone INTEGER NOT NULL DEFAULT 0
+    );",
+    // v6: 备份记录（原 backup_meta.json），id 为备份文件名；deleted_at 非空表示在回收区中
+    "CREATE TABLE IF NOT EXISTS backups (
+        id TEXT PRIMARY KEY,
+        original_file_name TEXT NOT NULL,
+        source_path TEXT,
+        ide_version TEXT,
+        size INTEGER,
+        sha256 TEXT,
+        bookmark_count INTEGER,
+        projects TEXT NOT NULL DEFAULT '[]',
+        label TEXT,
+        note TEXT,
+        pinned INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT NOT NULL,
+        deleted_at TEXT
+    );",
+    // v7: 记录备份来自哪台机器，用于识别同步目录中其它机器写入的备份
+    "ALTER TABLE backups ADD COLUMN origin_machine TEXT;
+    ALTER TABLE backups ADD COLUMN origin_host TEXT;",
+    // v8: 备份解析结果缓存，按备份文件的 SHA-256 判断是否失效
+    "CREATE TABLE IF NOT EXISTS backup_parse_cache (
+        backup_id TEXT PRIMARY KEY,
+        sha256 TEXT NOT NULL,
+        bookmarks TEXT NOT NULL,
+        parsed_at TEXT NOT NULL
+    );",
+    // v9: 操作历史中文件修改前后的内容，按 SHA-256 去重存放，payload 中只记录哈希
+    "CREATE TABLE IF NOT EXISTS journal_blobs (
+        sha256 TEXT PRIMARY KEY,
+        content BLOB NOT NULL
+    );",
+];
+
+fn run_migrations(conn: &Connection) -> Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", (i + 1) as i64)?;
+        tx.commit()?;
+        println!("Database migrated to version {}", i + 1);
+    }
+    Ok(())
 }
 
 pub fn init_db<R: Runtime>(app: &AppHandle<R>) -> Result<(), Box<dyn std::error::Error>> {
-    let db_path = get_db_path(app);
+    let db_path = get_db_path(app)?;
     let conn = Connection::open(db_path)?;
-
-    // Drop table to ensure schema update (User requested fresh start/fix)
-    conn.execute("DROP TABLE IF EXISTS bookmarks", [])?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS bookmarks (
-            id INTEGER PRIMARY KEY,
-            title TEXT NOT NULL,
-            file_path TEXT NOT NULL,
-            line_number INTEGER,
-            content TEXT,
-            created_at TEXT NOT NULL,
-            project TEXT DEFAULT 'Unknown'
-        )",
-        [],
-    )?;
+    run_migrations(&conn)?;
     Ok(())
 }
 
+/// 本地书签列表的筛选与分页条件，所有条件均可省略
+#[derive(Deserialize, Default, Debug)]
+pub struct BookmarkFilter {
+    pub project: Option<String>,
+    /// 文件路径包含该字符串
+    pub file_path: Option<String>,
+    /// 创建时间下限（含），RFC 3339 或 YYYY-MM-DD
+    pub created_from: Option<String>,
+    /// 创建时间上限（含），RFC 3339 或 YYYY-MM-DD
+    pub created_to: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// 分页查询结果，total 为满足筛选条件的总数
+#[derive(Serialize, Debug)]
+pub struct BookmarkPage {
+    pub items: Vec<Bookmark>,
+    pub total: i64,
+}
+
+const BOOKMARK_COLUMNS: &str =
+    "id, title, file_path, line_number, content, created_at, project, group_name, mnemonic";
+
+fn row_to_bookmark(row: &rusqlite::Row) -> Result<Bookmark> {
+    Ok(Bookmark {
+        id: Some(row.get(0)?),
+        title: row.get(1)?,
+        file_path: row.get(2)?,
+        line_number: row.get(3)?,
+        content: row.get(4)?,
+        created_at: Some(row.get(5)?),
+        project: row.get(6).unwrap_or("Unknown".to_string()),
+        group_name: row.get(7)?,
+        mnemonic: row.get(8)?,
+    })
+}
+
+/// 未显式指定项目时根据文件路径推断
+fn resolve_project(project: Option<String>, file_path: &str) -> String {
+    project
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| detect_project_name(file_path))
+}
+
+/// 读取指定 ID 的书签快照，不存在的 ID 被忽略
+pub(crate) fn load_bookmarks_by_ids(conn: &Connection, ids: &[i64]) -> Result<Vec<Bookmark>, String> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM bookmarks WHERE id = ?1", BOOKMARK_COLUMNS))
+        .map_err(|e| e.to_string())?;
+    let mut bookmarks = Vec::new();
+    for id in ids {
+        let mut rows = stmt.query_map(params![id], row_to_bookmark).map_err(|e| e.to_string())?;
+        if let Some(bookmark) = rows.next() {
+            bookmarks.push(bookmark.map_err(|e| e.to_string())?);
+        }
+    }
+    Ok(bookmarks)
+}
+
 #[tauri::command]
 pub fn get_bookmarks<R: Runtime>(app: AppHandle<R>) -> Result<Vec<Bookmark>, String> {
-    let db_path = get_db_path(&app);
-    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    let conn = open_db(&app)?;
 
     let mut stmt = conn
-        .prepare("SELECT id, title, file_path, line_number, content, created_at, project FROM bookmarks ORDER BY created_at DESC")
+        .prepare(&format!("SELECT {} FROM bookmarks ORDER BY created_at DESC", BOOKMARK_COLUMNS))
         .map_err(|e| e.to_string())?;
 
-    let bookmark_iter = stmt
-        .query_map([], |row| {
-            Ok(Bookmark {
-                id: Some(row.get(0)?),
-                title: row.get(1)?,
-                file_path: row.get(2)?,
-                line_number: row.get(3)?,
-                content: row.get(4)?,
-                created_at: Some(row.get(5)?),
-                project: row.get(6).unwrap_or("Unknown".to_string()),
-            })
-        })
-        .map_err(|e| e.to_string())?;
+    let bookmark_iter = stmt.query_map([], row_to_bookmark).map_err(|e| e.to_string())?;
 
     let mut bookmarks = Vec::new();
     for bookmark in bookmark_iter {
@@ -75,6 +287,81 @@ pub fn get_bookmarks<R: Runtime>(app: AppHandle<R>) -> Result<Vec<Bookmark>, Str
     Ok(bookmarks)
 }
 
+/// 按 ID 获取单个书签
+#[tauri::command]
+pub fn get_bookmark<R: Runtime>(app: AppHandle<R>, id: i64) -> Result<Bookmark, String> {
+    let conn = open_db(&app)?;
+    conn.query_row(
+        &format!("SELECT {} FROM bookmarks WHERE id = ?1", BOOKMARK_COLUMNS),
+        params![id],
+        row_to_bookmark,
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Bookmark {} not found", id),
+        e => e.to_string(),
+    })
+}
+
+/// 按项目、文件、创建时间筛选书签，支持分页
+#[tauri::command]
+pub fn list_bookmarks<R: Runtime>(app: AppHandle<R>, filter: Option<BookmarkFilter>) -> Result<BookmarkPage, String> {
+    let filter = filter.unwrap_or_default();
+    let conn = open_db(&app)?;
+
+    let mut conditions: Vec<String> = Vec::new();
+    let mut args: Vec<String> = Vec::new();
+    if let Some(project) = &filter.project {
+        args.push(project.clone());
+        conditions.push(format!("project = ?{}", args.len()));
+    }
+    if let Some(file_path) = &filter.file_path {
+        args.push(file_path.clone());
+        conditions.push(format!("instr(file_path, ?{}) > 0", args.len()));
+    }
+    if let Some(from) = &filter.created_from {
+        args.push(from.clone());
+        conditions.push(format!("created_at >= ?{}", args.len()));
+    }
+    if let Some(to) = &filter.created_to {
+        // 只比较与上限等长的前缀，使 YYYY-MM-DD 包含当天的全部书签
+        args.push(to.clone());
+        conditions.push(format!("substr(created_at, 1, length(?{0})) <= ?{0}", args.len()));
+    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+
+    let total: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM bookmarks{}", where_clause),
+            params_from_iter(args.iter()),
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    // LIMIT -1 表示不限制数量
+    let sql = format!(
+        "SELECT {} FROM bookmarks{} ORDER BY created_at DESC LIMIT {} OFFSET {}",
+        BOOKMARK_COLUMNS,
+        where_clause,
+        filter.limit.filter(|l| *l >= 0).unwrap_or(-1),
+        filter.offset.unwrap_or(0).max(0)
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params_from_iter(args.iter()), row_to_bookmark)
+        .map_err(|e| e.to_string())?;
+
+    let mut items = Vec::new();
+    for bookmark in rows {
+        items.push(bookmark.map_err(|e| e.to_string())?);
+    }
+    Ok(BookmarkPage { items, total })
+}
+
+/// 添加书签；project 省略时根据文件路径推断
 #[tauri::command]
 pub fn add_bookmark<R: Runtime>(
     app: AppHandle<R>,
@@ -82,156 +369,251 @@ pub fn add_bookmark<R: Runtime>(
     file_path: String,
     line_number: i32,
     content: String,
-) -> Result<(), String> {
-    let db_path = get_db_path(&app);
-    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    project: Option<String>,
+) -> Result<i64, String> {
+    let mut conn = open_db(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
 
     let now = Utc::now().to_rfc3339();
-    let project = extract_project_name(&file_path);
+    let project = resolve_project(project, &file_path);
 
-    conn.execute(
+    tx.execute(
         "INSERT INTO bookmarks (title, file_path, line_number, content, created_at, project) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
         params![title, file_path, line_number, content, now, project],
     )
     .map_err(|e| e.to_string())?;
+    let id = tx.last_insert_rowid();
 
-    Ok(())
+    let after = load_bookmarks_by_ids(&tx, &[id])?;
+    record_operation(&tx, &format!("添加书签 {}", title), &Operation::BookmarkRows { before: Vec::new(), after })?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(id)
 }
 
+/// 修改书签；project 省略时保留原有项目
 #[tauri::command]
-pub fn delete_bookmark<R: Runtime>(app: AppHandle<R>, id: i64) -> Result<(), String> {
-    let db_path = get_db_path(&app);
-    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+pub fn update_bookmark<R: Runtime>(
+    app: AppHandle<R>,
+    id: i64,
+    title: String,
+    file_path: String,
+    line_number: i32,
+    content: String,
+    project: Option<String>,
+) -> Result<Bookmark, String> {
+    let mut conn = open_db(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let before = load_bookmarks_by_ids(&tx, &[id])?;
 
-    conn.execute("DELETE FROM bookmarks WHERE id = ?1", params![id])
+    let changed = tx
+        .execute(
+            "UPDATE bookmarks SET title = ?1, file_path = ?2, line_number = ?3, content = ?4,
+                    project = COALESCE(?5, project)
+             WHERE id = ?6",
+            params![
+                title,
+                file_path,
+                line_number,
+                content,
+                project.map(|p| p.trim().to_string()).filter(|p| !p.is_empty()),
+                id
+            ],
+        )
         .map_err(|e| e.to_string())?;
+    if changed == 0 {
+        return Err(format!("Bookmark {} not found", id));
+    }
 
+    let after = load_bookmarks_by_ids(&tx, &[id])?;
+    record_operation(&tx, &format!("修改书签 {}", title), &Operation::BookmarkRows { before, after })?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    get_bookmark(app, id)
+}
+
+/// 将书签显式归入某个项目，返回更新的数量
+#[tauri::command]
+pub fn set_bookmark_project<R: Runtime>(app: AppHandle<R>, ids: Vec<i64>, project: String) -> Result<usize, String> {
+    let project = project.trim();
+    if project.is_empty() {
+        return Err("Project name cannot be empty".to_string());
+    }
+
+    let mut conn = open_db(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let before = load_bookmarks_by_ids(&tx, &ids)?;
+    let mut updated = 0;
+    for id in &ids {
+        updated += tx
+            .execute("UPDATE bookmarks SET project = ?1 WHERE id = ?2", params![project, id])
+            .map_err(|e| e.to_string())?;
+    }
+    let after = load_bookmarks_by_ids(&tx, &ids)?;
+    record_operation(
+        &tx,
+        &format!("将 {} 个书签归入项目 {}", updated, project),
+        &Operation::BookmarkRows { before, after },
+    )?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub fn delete_bookmark<R: Runtime>(app: AppHandle<R>, id: i64) -> Result<(), String> {
+    delete_bookmarks(app, vec![id])?;
     Ok(())
 }
 
-fn extract_project_name(path: &str) -> String {
-    let p = std::path::Path::new(path);
-    // Simple heuristic: Try to find "src" and take parent, or just take the parent of the file
-    // Enhanced: iterate components, look for standard structure
-    for component in p.components() {
-         if let Some(s) = component.as_os_str().to_str() {
-             if s.eq_ignore_ascii_case("src") {
-                 // Return the component before src?
-                 let _parent = p.parent().unwrap_or(p); // This is just the file's parent if passed file
-                 // Correct logic: we need to find where 'src' is in the path string
-                 // Easier: split by 'src'
-                 let parts: Vec<&str> = path.split("src").collect();
-                 if !parts.is_empty() {
-                     let pre_src = std::path::Path::new(parts[0]);
-                     return pre_src.file_name()
-                        .map(|n| n.to_string_lossy().to_string())
-                        .unwrap_or("Unknown".to_string());
-                 }
-             }
-         }
-    }
-    // Fallback: take parent directory name
-    p.parent()
-        .and_then(|parent| parent.file_name())
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or("Unknown".to_string())
+/// 批量删除书签，返回实际删除的数量
+#[tauri::command]
+pub fn delete_bookmarks<R: Runtime>(app: AppHandle<R>, ids: Vec<i64>) -> Result<usize, String> {
+    let mut conn = open_db(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let before = load_bookmarks_by_ids(&tx, &ids)?;
+    let mut deleted = 0;
+    for id in &ids {
+        deleted += tx
+            .execute("DELETE FROM bookmarks WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+    }
+    if deleted > 0 {
+        let summary = match before.as_slice() {
+            [single] => format!("删除书签 {}", single.title),
+            _ => format!("删除 {} 个书签", deleted),
+        };
+        record_operation(&tx, &summary, &Operation::BookmarkRows { before, after: Vec::new() })?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(deleted)
 }
 
+/// 推断导入文件中 $PROJECT_DIR$ 对应的项目根目录
+/// 旧格式位于 <项目>/.idea/ 下；新格式位于 <配置目录>/workspace/ 下，按分组名在最近项目中查找
+fn resolve_import_project_root(xml_path: &Path, group_name: &str) -> Option<String> {
+    let parent = xml_path.parent()?;
+    if parent.file_name().is_some_and(|n| n == ".idea") {
+        return parent.parent().map(|p| p.to_string_lossy().to_string());
+    }
+
+    let config_dir = parent.parent()?;
+    get_recent_projects(&config_dir.join("options"))
+        .into_iter()
+        .find(|p| p.file_name().is_some_and(|n| n.to_string_lossy() == group_name))
+        .map(|p| p.to_string_lossy().to_string())
+}
+
+/// 从 IDEA 书签 XML 导入书签到本地数据库，支持 2025+ 全局 workspace 格式与旧版 .idea 格式
+/// project_root 用于解析 $PROJECT_DIR$，省略时自动推断
 #[tauri::command]
-pub fn import_bookmarks<R: Runtime>(app: AppHandle<R>, file_path: String) -> Result<String, String> {
+pub fn import_bookmarks<R: Runtime>(
+    app: AppHandle<R>,
+    file_path: String,
+    project_root: Option<String>,
+) -> Result<ImportReport, String> {
     println!("Starting import from: {}", file_path);
-    let content = std::fs::read_to_string(&file_path).map_err(|e| e.to_string())?;
-    
-    // Determine project root only for relative paths
-    let path_obj = std::path::Path::new(&file_path);
-    let project_root = path_obj.parent()
-        .and_then(|p| p.parent())
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_default();
+    let xml_path = Path::new(&file_path);
+    let content = std::fs::read_to_string(xml_path).map_err(|e| e.to_string())?;
 
-    println!("Project root context (if needed): {}", project_root);
+    let parsed = if content.contains("BookmarksManager") {
+        parse_bookmarks_from_global_workspace(xml_path)
+    } else {
+        // 旧格式以 .idea 所在目录名作为项目名
+        let project_name = xml_path
+            .parent()
+            .filter(|p| p.file_name().is_some_and(|n| n == ".idea"))
+            .and_then(|p| p.parent())
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Unknown".to_string());
+        parse_bookmarks_from_project_idea(xml_path, &project_name)
+    };
 
-    let db_path = get_db_path(&app);
-    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    let mut report = ImportReport::default();
+    if parsed.is_empty() {
+        report.failed.push(ImportFailure {
+            file_path: Some(file_path),
+            line_number: None,
+            reason: "文件中未找到可识别的书签".to_string(),
+        });
+        return Ok(report);
+    }
+
+    let mut conn = open_db(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
     let now = Utc::now().to_rfc3339();
+    let mut inserted_ids = Vec::new();
 
-    // Helper closure for attribute extraction
-    let extract_attr = |chunk: &str, attr: &str| -> Option<String> {
-        let patterns = [format!("{}=\"", attr), format!("{}='", attr)];
-        for p in patterns {
-             if let Some(idx) = chunk.find(&p) {
-                let start = idx + p.len();
-                let quote = p.chars().last().unwrap();
-                if let Some(end) = chunk[start..].find(quote) {
-                    return Some(chunk[start..start+end].to_string());
+    for b in parsed {
+        let resolved_path = match b.file_path.strip_prefix(PROJECT_DIR_PLACEHOLDER) {
+            Some(rest) => {
+                let root = project_root
+                    .clone()
+                    .or_else(|| resolve_import_project_root(xml_path, &b.project_name));
+                match root {
+                    Some(root) => format!("{}{}", root.trim_end_matches(['/', '\\']), rest),
+                    None => {
+                        report.failed.push(ImportFailure {
+                            file_path: Some(b.file_path),
+                            line_number: Some(b.line_number),
+                            reason: "无法解析 $PROJECT_DIR$，请指定项目根目录".to_string(),
+                        });
+                        continue;
+                    }
                 }
-             }
-        }
-        None
-    };
-
-    let chunks: Vec<&str> = content.split("<bookmark").collect();
-    let mut count = 0;
-    
-    // Log the first chunk to see what we are dealing with (it might be the header)
-    if !chunks.is_empty() {
-        println!("Header chunk info: {}...", &chunks[0].chars().take(50).collect::<String>());
-    }
-
-    for (i, chunk) in chunks.iter().skip(1).enumerate() {
-        let url_opt = extract_attr(chunk, "url");
-        if let Some(raw_url) = url_opt {
-            let mut file_path = raw_url.clone();
-            
-            // Handle $PROJECT_DIR$
-            if file_path.contains("$PROJECT_DIR$") {
-                file_path = file_path.replace("file://$PROJECT_DIR$", &project_root);
-            } else {
-                // Handle absolute paths like file://C:/...
-                file_path = file_path.replace("file://", "");
-            }
-            // Removing extra file:/ if present (sometimes file:/C:/...)
-            if file_path.starts_with('/') && file_path.chars().nth(2) == Some(':') {
-                 // e.g. /C:/Users... -> C:/Users... on Windows
-                 file_path = file_path[1..].to_string();
             }
+            None => b.file_path.clone(),
+        };
 
-            let line_number = extract_attr(chunk, "line")
-                .and_then(|s| s.parse::<i32>().ok())
-                .map(|l| l + 1)
-                .unwrap_or(1);
-            
-            let description = extract_attr(chunk, "description").unwrap_or_default();
-            
-            let title = std::path::Path::new(&file_path)
-                .file_name()
-                .map(|n| n.to_string_lossy().to_string())
-                .unwrap_or_else(|| "Unknown File".to_string());
-            
-            let project = extract_project_name(&file_path);
-
-            let exists: bool = conn.query_row(
-                "SELECT EXISTS(SELECT 1 FROM bookmarks WHERE file_path = ?1 AND line_number = ?2)",
-                params![file_path, line_number],
-                |row| row.get(0),
-            ).unwrap_or(false);
-
-            if !exists {
-                 match conn.execute(
-                    "INSERT INTO bookmarks (title, file_path, line_number, content, created_at, project) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                    params![title, file_path, line_number, description, now, project],
-                ) {
-                    Ok(_) => count += 1,
-                    Err(e) => println!("Failed to insert bookmark {}: {}", i, e),
-                }
-            } else {
-                println!("Bookmark {} ({}) already exists.", i, title);
+        let entry = ImportEntry {
+            file_path: resolved_path.clone(),
+            line_number: b.line_number,
+            group_name: b.project_name.clone(),
+        };
+
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM bookmarks WHERE file_path = ?1 AND line_number = ?2)",
+            params![resolved_path, b.line_number],
+            |row| row.get(0),
+        ).unwrap_or(false);
+        if exists {
+            report.skipped_duplicate.push(entry);
+            continue;
+        }
+
+        let project = detect_project_name(&resolved_path);
+        match tx.execute(
+            "INSERT INTO bookmarks (title, file_path, line_number, content, created_at, project, group_name, mnemonic) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![b.file_name, resolved_path, b.line_number, b.description, now, project, b.project_name, b.mnemonic],
+        ) {
+            Ok(_) => {
+                inserted_ids.push(tx.last_insert_rowid());
+                report.imported.push(entry);
             }
-        } else {
-            println!("Chunk {} skipped: no url found. Content start: {}", i, &chunk.chars().take(20).collect::<String>());
+            Err(e) => report.failed.push(ImportFailure {
+                file_path: Some(resolved_path),
+                line_number: Some(b.line_number),
+                reason: e.to_string(),
+            }),
         }
     }
 
-    println!("Import finished. Imported {} bookmarks.", count);
-    Ok(format!("Successfully imported {} bookmarks", count))
+    if !inserted_ids.is_empty() {
+        let after = load_bookmarks_by_ids(&tx, &inserted_ids)?;
+        let file_name = xml_path.file_name().unwrap_or_default().to_string_lossy();
+        record_operation(
+            &tx,
+            &format!("从 {} 导入 {} 个书签", file_name, inserted_ids.len()),
+            &Operation::BookmarkRows { before: Vec::new(), after },
+        )?;
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+    println!(
+        "Import finished. Imported {}, skipped {}, failed {}.",
+        report.imported.len(),
+        report.skipped_duplicate.len(),
+        report.failed.len()
+    );
+    Ok(report)
 }
@@ -0,0 +1,428 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use chrono::Local;
+
+use crate::bookmark_manager::{read_bookmarks_from_workspace, ParsedBookmark};
+
+/// JSON 导出格式的版本号，结构变化时递增
+const EXPORT_SCHEMA_VERSION: u32 = 1;
+/// 代码片段默认上下各取的行数
+const SNIPPET_RADIUS: i32 = 2;
+
+#[derive(serde::Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Csv,
+    Json,
+}
+
+/// 导出筛选条件，所有字段均可省略
+#[derive(serde::Deserialize, Default, Debug)]
+pub struct ExportFilter {
+    /// 只导出这些项目
+    pub projects: Option<Vec<String>>,
+    /// 文件名、路径或描述中包含的关键字（不区分大小写）
+    pub keyword: Option<String>,
+    /// 只导出助记符书签
+    #[serde(default)]
+    pub mnemonic_only: bool,
+}
+
+/// 代码片段中的一行
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct SnippetLine {
+    pub line_number: i32,
+    pub text: String,
+    /// 是否为书签所在行
+    pub is_bookmark: bool,
+}
+
+#[derive(serde::Serialize)]
+struct JsonBookmark<'a> {
+    #[serde(flatten)]
+    bookmark: &'a ParsedBookmark,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snippet: Option<Vec<SnippetLine>>,
+}
+
+#[derive(serde::Serialize)]
+struct JsonProject<'a> {
+    name: &'a str,
+    bookmarks: Vec<JsonBookmark<'a>>,
+}
+
+#[derive(serde::Serialize)]
+struct JsonExport<'a> {
+    schema: &'static str,
+    version: u32,
+    exported_at: String,
+    source: &'a str,
+    total: usize,
+    projects: Vec<JsonProject<'a>>,
+}
+
+impl ExportFilter {
+    fn matches(&self, bookmark: &ParsedBookmark) -> bool {
+        if let Some(projects) = &self.projects {
+            if !projects.is_empty() && !projects.contains(&bookmark.project_name) {
+                return false;
+            }
+        }
+        if self.mnemonic_only && bookmark.mnemonic.is_none() {
+            return false;
+        }
+        if let Some(keyword) = self.keyword.as_deref().map(str::trim).filter(|k| !k.is_empty()) {
+            let keyword = keyword.to_lowercase();
+            let haystacks = [&bookmark.file_name, &bookmark.file_path, &bookmark.description];
+            if !haystacks.iter().any(|h| h.to_lowercase().contains(&keyword)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 读取书签所在行附近的代码，文件不存在（如未解析的 $PROJECT_DIR$ 路径）时返回 None
+pub(crate) fn read_snippet(file_path: &str, line_number: i32, radius: i32) -> Option<Vec<SnippetLine>> {
+    let content = fs::read_to_string(file_path).ok()?;
+    let lines: Vec<&str> = content.lines().collect();
+    if line_number < 1 || line_number as usize > lines.len() {
+        return None;
+    }
+
+    let start = (line_number - radius).max(1);
+    let end = (line_number + radius).min(lines.len() as i32);
+    Some(
+        (start..=end)
+            .map(|n| SnippetLine {
+                line_number: n,
+                text: lines[(n - 1) as usize].to_string(),
+                is_bookmark: n == line_number,
+            })
+            .collect(),
+    )
+}
+
+/// 根据文件扩展名推断 Markdown 代码块的语言标识
+pub(crate) fn code_language(file_name: &str) -> &'static str {
+    let ext = Path::new(file_name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" => "typescript",
+        "vue" => "vue",
+        "go" => "go",
+        "xml" => "xml",
+        "sql" => "sql",
+        "yml" | "yaml" => "yaml",
+        "json" => "json",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "hpp" => "cpp",
+        _ => "",
+    }
+}
+
+/// 渲染 Markdown 代码块；围栏比片段中最长的连续反引号多一个（至少三个），避免代码中的 ``` 提前结束代码块
+pub(crate) fn render_code_block(snippet: &[SnippetLine], language: &str) -> String {
+    let longest_run = snippet
+        .iter()
+        .flat_map(|line| line.text.split(|c| c != '`'))
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat((longest_run + 1).max(3));
+
+    let mut out = format!("{}{}\n", fence, language);
+    for line in snippet {
+        out.push_str(&line.text);
+        out.push('\n');
+    }
+    out.push_str(&fence);
+    out.push_str("\n\n");
+    out
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn escape_csv(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') || s.contains('\r') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn group_by_project(bookmarks: &[ParsedBookmark]) -> BTreeMap<&str, Vec<&ParsedBookmark>> {
+    let mut groups: BTreeMap<&str, Vec<&ParsedBookmark>> = BTreeMap::new();
+    for b in bookmarks {
+        groups.entry(b.project_name.as_str()).or_default().push(b);
+    }
+    groups
+}
+
+fn render_markdown(bookmarks: &[ParsedBookmark], include_snippets: bool) -> String {
+    let mut out = String::new();
+    out.push_str("# IDEA Mark 书签导出\n\n");
+    out.push_str(&format!(
+        "- 导出时间：{}\n- 书签总数：{}\n\n",
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        bookmarks.len()
+    ));
+
+    for (project, items) in group_by_project(bookmarks) {
+        out.push_str(&format!("## {} ({})\n\n", project, items.len()));
+        for b in items {
+            let title = if b.description.is_empty() { &b.file_name } else { &b.description };
+            out.push_str(&format!("### {}\n\n", title));
+            out.push_str(&format!("- 位置：`{}:{}`\n", b.file_path, b.line_number));
+            if let Some(m) = &b.mnemonic {
+                out.push_str(&format!("- 助记符：`{}`\n", m));
+            }
+            out.push('\n');
+
+            if include_snippets {
+                if let Some(snippet) = read_snippet(&b.file_path, b.line_number, SNIPPET_RADIUS) {
+                    out.push_str(&render_code_block(&snippet, code_language(&b.file_name)));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn render_html(bookmarks: &[ParsedBookmark], include_snippets: bool) -> String {
+    let mut body = String::new();
+    for (project, items) in group_by_project(bookmarks) {
+        body.push_str(&format!(
+            "<section>\n<h2>{} <small>({})</small></h2>\n<table>\n<thead><tr><th>文件</th><th>行号</th><th>助记符</th><th>描述</th></tr></thead>\n<tbody>\n",
+            escape_html(project),
+            items.len()
+        ));
+        for b in items {
+            body.push_str(&format!(
+                "<tr><td title=\"{}\">{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&b.file_path),
+                escape_html(&b.file_name),
+                b.line_number,
+                escape_html(b.mnemonic.as_deref().unwrap_or("")),
+                escape_html(&b.description)
+            ));
+            if include_snippets {
+                if let Some(snippet) = read_snippet(&b.file_path, b.line_number, SNIPPET_RADIUS) {
+                    body.push_str("<tr class=\"snippet\"><td colspan=\"4\"><pre>");
+                    for line in snippet {
+                        let text = format!("{:>5}  {}", line.line_number, escape_html(&line.text));
+                        if line.is_bookmark {
+                            body.push_str(&format!("<mark>{}</mark>\n", text));
+                        } else {
+                            body.push_str(&format!("{}\n", text));
+                        }
+                    }
+                    body.push_str("</pre></td></tr>\n");
+                }
+            }
+        }
+        body.push_str("</tbody>\n</table>\n</section>\n");
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+<meta charset="UTF-8">
+<title>IDEA Mark 书签报告</title>
+<style>
+body {{ font-family: -apple-system, "Segoe UI", "Microsoft YaHei", sans-serif; margin: 32px; color: #303133; }}
+h1 {{ font-size: 24px; }}
+h2 {{ font-size: 18px; border-bottom: 1px solid #ebeef5; padding-bottom: 6px; }}
+table {{ border-collapse: collapse; width: 100%; margin-bottom: 24px; }}
+th, td {{ border: 1px solid #ebeef5; padding: 6px 10px; text-align: left; font-size: 14px; }}
+th {{ background: #f5f7fa; }}
+tr.snippet td {{ background: #fafafa; }}
+pre {{ margin: 0; font-family: Consolas, Menlo, monospace; font-size: 12px; }}
+mark {{ background: #fdf6ec; }}
+</style>
+</head>
+<body>
+<h1>IDEA Mark 书签报告</h1>
+<p>导出时间：{} ，书签总数：{}</p>
+{}</body>
+</html>
+"#,
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        bookmarks.len(),
+        body
+    )
+}
+
+fn render_csv(bookmarks: &[ParsedBookmark]) -> String {
+    // 写入 BOM，保证 Excel 正确识别 UTF-8 中文
+    let mut out = String::from("\u{feff}project,file_name,file_path,line_number,description,mnemonic,bookmark_type\n");
+    for b in bookmarks {
+        let fields = [
+            escape_csv(&b.project_name),
+            escape_csv(&b.file_name),
+            escape_csv(&b.file_path),
+            b.line_number.to_string(),
+            escape_csv(&b.description),
+            escape_csv(b.mnemonic.as_deref().unwrap_or("")),
+            escape_csv(&b.bookmark_type),
+        ];
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn render_json(bookmarks: &[ParsedBookmark], include_snippets: bool, source: &str) -> Result<String, String> {
+    let projects = group_by_project(bookmarks)
+        .into_iter()
+        .map(|(name, items)| JsonProject {
+            name,
+            bookmarks: items
+                .into_iter()
+                .map(|b| JsonBookmark {
+                    bookmark: b,
+                    snippet: if include_snippets {
+                        read_snippet(&b.file_path, b.line_number, SNIPPET_RADIUS)
+                    } else {
+                        None
+                    },
+                })
+                .collect(),
+        })
+        .collect();
+
+    let export = JsonExport {
+        schema: "ideamark.bookmarks",
+        version: EXPORT_SCHEMA_VERSION,
+        exported_at: Local::now().to_rfc3339(),
+        source,
+        total: bookmarks.len(),
+        projects,
+    };
+    serde_json::to_string_pretty(&export).map_err(|e| e.to_string())
+}
+
+/// 将书签渲染为指定格式的文本
+pub(crate) fn render_export(
+    bookmarks: &[ParsedBookmark],
+    format: ExportFormat,
+    include_snippets: bool,
+    source: &str,
+) -> Result<String, String> {
+    match format {
+        ExportFormat::Markdown => Ok(render_markdown(bookmarks, include_snippets)),
+        ExportFormat::Html => Ok(render_html(bookmarks, include_snippets)),
+        ExportFormat::Csv => Ok(render_csv(bookmarks)),
+        ExportFormat::Json => render_json(bookmarks, include_snippets, source),
+    }
+}
+
+/// 导出 workspace 中的书签为 Markdown / HTML / CSV / JSON 报告，返回导出的书签数量
+#[tauri::command]
+pub fn export_bookmarks(
+    workspace_path: String,
+    format: ExportFormat,
+    output_path: String,
+    filter: Option<ExportFilter>,
+    include_snippets: Option<bool>,
+) -> Result<usize, String> {
+    let filter = filter.unwrap_or_default();
+    let bookmarks: Vec<ParsedBookmark> = read_bookmarks_from_workspace(workspace_path.clone())?
+        .into_iter()
+        .filter(|b| filter.matches(b))
+        .collect();
+
+    let content = render_export(&bookmarks, format, include_snippets.unwrap_or(true), &workspace_path)?;
+
+    if let Some(parent) = Path::new(&output_path).parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(&output_path, content).map_err(|e| e.to_string())?;
+
+    println!("Exported {} bookmarks to {}", bookmarks.len(), output_path);
+    Ok(bookmarks.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(project: &str, file_path: &str, description: &str, mnemonic: Option<&str>) -> ParsedBookmark {
+        ParsedBookmark {
+            project_name: project.to_string(),
+            file_name: Path::new(file_path).file_name().unwrap().to_string_lossy().to_string(),
+            file_path: file_path.to_string(),
+            line_number: 1,
+            description: description.to_string(),
+            mnemonic: mnemonic.map(str::to_string),
+            bookmark_type: String::new(),
+        }
+    }
+
+    fn snippet(lines: &[&str]) -> Vec<SnippetLine> {
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, text)| SnippetLine { line_number: i as i32 + 1, text: text.to_string(), is_bookmark: false })
+            .collect()
+    }
+
+    #[test]
+    fn escapes_csv_fields() {
+        assert_eq!(escape_csv("plain"), "plain");
+        assert_eq!(escape_csv("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv("two\nlines"), "\"two\nlines\"");
+        assert_eq!(escape_csv("cr\r"), "\"cr\r\"");
+    }
+
+    #[test]
+    fn escapes_html_text() {
+        assert_eq!(escape_html("<a href=\"x\">&</a>"), "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+        assert_eq!(escape_html("&lt;"), "&amp;lt;");
+    }
+
+    #[test]
+    fn filter_by_project_keyword_and_mnemonic() {
+        let b = bookmark("core", "/src/Parser.java", "Entry point", Some("1"));
+        let anonymous = bookmark("web", "/src/app.js", "", None);
+
+        assert!(ExportFilter::default().matches(&b));
+        let projects = ExportFilter { projects: Some(vec!["core".into()]), ..Default::default() };
+        assert!(projects.matches(&b) && !projects.matches(&anonymous));
+        // 空的项目列表不做限制
+        assert!(ExportFilter { projects: Some(Vec::new()), ..Default::default() }.matches(&anonymous));
+
+        let keyword = ExportFilter { keyword: Some(" ENTRY ".into()), ..Default::default() };
+        assert!(keyword.matches(&b) && !keyword.matches(&anonymous));
+        assert!(ExportFilter { keyword: Some("parser".into()), ..Default::default() }.matches(&b));
+
+        let mnemonic = ExportFilter { mnemonic_only: true, ..Default::default() };
+        assert!(mnemonic.matches(&b) && !mnemonic.matches(&anonymous));
+    }
+
+    #[test]
+    fn code_fence_outgrows_backticks_in_snippet() {
+        assert_eq!(render_code_block(&snippet(&["let x = 1;"]), "rust"), "```rust\nlet x = 1;\n```\n\n");
+        let block = render_code_block(&snippet(&["Example:", "```kotlin", "val a = 1", "```"]), "md");
+        assert!(block.starts_with("````md\n") && block.ends_with("\n````\n\n"));
+        let block = render_code_block(&snippet(&["`````"]), "");
+        assert!(block.starts_with("``````\n"));
+    }
+}
@@ -0,0 +1,336 @@
+use std::path::Path;
+use std::process::Command;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, OptionalExtension};
+use tauri::{AppHandle, Runtime};
+
+use crate::db::open_db;
+
+/// 书签行的 blame 信息
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct BlameInfo {
+    pub commit: String,
+    pub author: String,
+    pub author_email: String,
+    /// 提交时间 (RFC 3339)
+    pub date: String,
+    pub summary: String,
+    /// 该行尚未提交（工作区修改）
+    pub uncommitted: bool,
+}
+
+/// 书签行号从记录时的提交映射到 HEAD 的结果
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct LineMapping {
+    pub file_path: String,
+    pub original_line: i32,
+    pub captured_commit: String,
+    pub head_commit: String,
+    /// 映射后的行号，所在行被删除时为空
+    pub mapped_line: Option<i32>,
+    /// unchanged / shifted / modified / deleted
+    pub status: String,
+}
+
+/// 书签所在的 Git 仓库信息
+struct RepoLocation {
+    /// 文件所在目录，作为 git 命令的工作目录
+    dir: String,
+    /// 文件名（相对于 dir）
+    file_name: String,
+}
+
+/// 执行本地 git 命令（不涉及网络），返回标准输出
+fn run_git(dir: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn locate_in_repo(file_path: &str) -> Result<RepoLocation, String> {
+    let path = Path::new(file_path);
+    let dir = path
+        .parent()
+        .ok_or("Invalid file path")?
+        .to_string_lossy()
+        .to_string();
+    let file_name = path
+        .file_name()
+        .ok_or("Invalid file path")?
+        .to_string_lossy()
+        .to_string();
+
+    run_git(&dir, &["rev-parse", "--show-toplevel"])
+        .map_err(|e| format!("Not inside a git repository: {}", e))?;
+
+    Ok(RepoLocation { dir, file_name })
+}
+
+fn head_commit(dir: &str) -> Result<String, String> {
+    run_git(dir, &["rev-parse", "HEAD"]).map(|s| s.trim().to_string())
+}
+
+/// 提交哈希只能是 7~40 位十六进制，避免以 - 开头的参数被 git 当作选项
+fn is_commit_hash(commit: &str) -> bool {
+    (7..=40).contains(&commit.len()) && commit.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+/// 校验并解析为完整的提交哈希
+fn resolve_commit(dir: &str, commit: &str) -> Result<String, String> {
+    if !is_commit_hash(commit) {
+        return Err(format!("Invalid commit hash: {}", commit));
+    }
+    run_git(dir, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", commit)])
+        .map(|s| s.trim().to_string())
+        .map_err(|_| format!("Unknown commit: {}", commit))
+}
+
+/// 解析 hunk 头 "@@ -a,b +c,d @@"，返回 (a, b, c, d)
+fn parse_hunk_header(line: &str) -> Option<(i32, i32, i32, i32)> {
+    let mut parts = line.split_whitespace().skip(1);
+    let old = parts.next()?.strip_prefix('-')?;
+    let new = parts.next()?.strip_prefix('+')?;
+
+    let split = |s: &str| -> Option<(i32, i32)> {
+        match s.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((s.parse().ok()?, 1)),
+        }
+    };
+    let (a, b) = split(old)?;
+    let (c, d) = split(new)?;
+    Some((a, b, c, d))
+}
+
+/// 根据 `git diff -U0` 的输出把旧行号映射为新行号，返回 (新行号, 状态)
+fn map_line_through_diff(diff: &str, line: i32) -> (Option<i32>, &'static str) {
+    if diff.lines().any(|l| l.starts_with("+++ /dev/null")) {
+        return (None, "deleted");
+    }
+
+    let mut offset = 0;
+    for header in diff.lines().filter(|l| l.starts_with("@@")) {
+        let (old_start, old_count, new_start, new_count) = match parse_hunk_header(header) {
+            Some(h) => h,
+            None => continue,
+        };
+
+        if old_count == 0 {
+            // 纯插入：插入发生在旧文件第 old_start 行之后
+            if line > old_start {
+                offset += new_count;
+                continue;
+            }
+            break;
+        }
+
+        let old_end = old_start + old_count - 1;
+        if line < old_start {
+            break;
+        }
+        if line <= old_end {
+            // 书签行本身被修改或删除，指向替换内容的起始位置
+            if new_count == 0 {
+                return (None, "deleted");
+            }
+            return (Some(new_start), "modified");
+        }
+        offset += new_count - old_count;
+    }
+
+    let status = if offset == 0 { "unchanged" } else { "shifted" };
+    (Some(line + offset), status)
+}
+
+fn parse_blame_porcelain(output: &str) -> Option<BlameInfo> {
+    let mut lines = output.lines();
+    let commit = lines.next()?.split_whitespace().next()?.to_string();
+
+    let mut info = BlameInfo {
+        uncommitted: commit.chars().all(|c| c == '0'),
+        commit,
+        author: String::new(),
+        author_email: String::new(),
+        date: String::new(),
+        summary: String::new(),
+    };
+
+    for line in lines {
+        if let Some(v) = line.strip_prefix("author-mail ") {
+            info.author_email = v.trim_matches(|c| c == '<' || c == '>').to_string();
+        } else if let Some(v) = line.strip_prefix("author-time ") {
+            info.date = v
+                .trim()
+                .parse::<i64>()
+                .ok()
+                .and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0))
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_default();
+        } else if let Some(v) = line.strip_prefix("author ") {
+            info.author = v.to_string();
+        } else if let Some(v) = line.strip_prefix("summary ") {
+            info.summary = v.to_string();
+        } else if line.starts_with('\t') {
+            break;
+        }
+    }
+    Some(info)
+}
+
+/// 记录书签创建时所在的提交，供之后映射到 HEAD
+#[tauri::command]
+pub fn record_bookmark_commit<R: Runtime>(
+    app: AppHandle<R>,
+    file_path: String,
+    line_number: i32,
+) -> Result<String, String> {
+    let location = locate_in_repo(&file_path)?;
+    let commit = head_commit(&location.dir)?;
+
+    let conn = open_db(&app)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO bookmark_commits (file_path, line_number, commit_hash, captured_at) VALUES (?1, ?2, ?3, ?4)",
+        params![file_path, line_number, commit, Utc::now().to_rfc3339()],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(commit)
+}
+
+/// 将书签行号从记录时的提交（或指定提交）经过 diff 映射到 HEAD
+#[tauri::command]
+pub fn map_bookmark_line_to_head<R: Runtime>(
+    app: AppHandle<R>,
+    file_path: String,
+    line_number: i32,
+    commit: Option<String>,
+) -> Result<LineMapping, String> {
+    let captured_commit = match commit {
+        Some(c) => c,
+        None => {
+            let conn = open_db(&app)?;
+            conn.query_row(
+                "SELECT commit_hash FROM bookmark_commits WHERE file_path = ?1 AND line_number = ?2",
+                params![file_path, line_number],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .ok_or("No commit recorded for this bookmark")?
+        }
+    };
+
+    let location = locate_in_repo(&file_path)?;
+    let captured_commit = resolve_commit(&location.dir, &captured_commit)?;
+    let head = head_commit(&location.dir)?;
+
+    let (mapped_line, status) = if head == captured_commit {
+        (Some(line_number), "unchanged")
+    } else {
+        let diff = run_git(
+            &location.dir,
+            &[
+                "diff",
+                "-U0",
+                "--no-color",
+                "--no-ext-diff",
+                "--end-of-options",
+                &captured_commit,
+                &head,
+                "--",
+                &location.file_name,
+            ],
+        )?;
+        map_line_through_diff(&diff, line_number)
+    };
+
+    Ok(LineMapping {
+        file_path,
+        original_line: line_number,
+        captured_commit,
+        head_commit: head,
+        mapped_line,
+        status: status.to_string(),
+    })
+}
+
+/// 获取书签行的 blame 信息（作者、提交、时间）
+#[tauri::command]
+pub fn get_bookmark_blame(file_path: String, line_number: i32) -> Result<BlameInfo, String> {
+    let location = locate_in_repo(&file_path)?;
+    let range = format!("{},{}", line_number, line_number);
+    let output = run_git(
+        &location.dir,
+        &["blame", "--porcelain", "-L", &range, "--", &location.file_name],
+    )?;
+
+    parse_blame_porcelain(&output).ok_or_else(|| "Failed to parse git blame output".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hunk_headers() {
+        assert_eq!(parse_hunk_header("@@ -10,2 +12,3 @@ fn main()"), Some((10, 2, 12, 3)));
+        assert_eq!(parse_hunk_header("@@ -5 +5 @@"), Some((5, 1, 5, 1)));
+        assert_eq!(parse_hunk_header("@@ -3,0 +4,2 @@"), Some((3, 0, 4, 2)));
+        assert_eq!(parse_hunk_header("not a hunk"), None);
+    }
+
+    #[test]
+    fn insertion_before_line_shifts_it() {
+        let diff = "@@ -2,0 +3,2 @@\n+a\n+b\n";
+        assert_eq!(map_line_through_diff(diff, 5), (Some(7), "shifted"));
+    }
+
+    #[test]
+    fn insertion_after_line_keeps_it() {
+        let diff = "@@ -8,0 +9,3 @@\n+a\n+b\n+c\n";
+        assert_eq!(map_line_through_diff(diff, 5), (Some(5), "unchanged"));
+        // 紧跟在书签行之后插入
+        assert_eq!(map_line_through_diff("@@ -5,0 +6 @@\n+a\n", 5), (Some(5), "unchanged"));
+    }
+
+    #[test]
+    fn modified_line_points_to_replacement() {
+        let diff = "@@ -4,3 +4,2 @@\n-x\n-y\n-z\n+p\n+q\n";
+        assert_eq!(map_line_through_diff(diff, 5), (Some(4), "modified"));
+        assert_eq!(map_line_through_diff(diff, 9), (Some(8), "shifted"));
+    }
+
+    #[test]
+    fn deleted_line_and_file() {
+        assert_eq!(map_line_through_diff("@@ -5,1 +4,0 @@\n-x\n", 5), (None, "deleted"));
+        assert_eq!(map_line_through_diff("@@ -5,1 +4,0 @@\n-x\n", 6), (Some(5), "shifted"));
+        let removed = "--- a/x.rs\n+++ /dev/null\n@@ -1,3 +0,0 @@\n";
+        assert_eq!(map_line_through_diff(removed, 2), (None, "deleted"));
+    }
+
+    #[test]
+    fn multiple_hunks_accumulate_offsets() {
+        let diff = "@@ -1,0 +2,2 @@\n+a\n+b\n@@ -10,3 +12,1 @@\n-x\n-y\n-z\n+w\n@@ -30,0 +31 @@\n+c\n";
+        assert_eq!(map_line_through_diff(diff, 5), (Some(7), "shifted"));
+        assert_eq!(map_line_through_diff(diff, 20), (Some(20), "unchanged"));
+        assert_eq!(map_line_through_diff(diff, 40), (Some(41), "shifted"));
+    }
+
+    #[test]
+    fn rejects_non_hash_commits() {
+        assert!(is_commit_hash("0123abc"));
+        assert!(is_commit_hash(&"f".repeat(40)));
+        assert!(!is_commit_hash("--output=/tmp/x"));
+        assert!(!is_commit_hash("HEAD~1"));
+        assert!(!is_commit_hash("0123ABC"));
+        assert!(!is_commit_hash(&"f".repeat(41)));
+    }
+}
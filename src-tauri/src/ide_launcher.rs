@@ -0,0 +1,168 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::preferences::load_prefs;
+
+/// 根据配置目录名（如 IntelliJIdea2025.1、PyCharm2024.3）推断启动脚本名
+fn launcher_script_name(product_dir_name: &str) -> &'static str {
+    const PRODUCTS: [(&str, &str); 12] = [
+        ("IntelliJIdea", "idea"),
+        ("IdeaIC", "idea"),
+        ("PyCharm", "pycharm"),
+        ("WebStorm", "webstorm"),
+        ("GoLand", "goland"),
+        ("CLion", "clion"),
+        ("PhpStorm", "phpstorm"),
+        ("RubyMine", "rubymine"),
+        ("Rider", "rider"),
+        ("DataGrip", "datagrip"),
+        ("RustRover", "rustrover"),
+        ("AndroidStudio", "studio"),
+    ];
+    PRODUCTS
+        .iter()
+        .find(|(prefix, _)| product_dir_name.starts_with(prefix))
+        .map(|(_, script)| *script)
+        .unwrap_or("idea")
+}
+
+/// 读取 IDE 写入的 .home 文件，得到安装目录
+/// .home 位于系统目录（Windows: LocalAppData/JetBrains/<产品>，Linux: ~/.cache/JetBrains/<产品>）
+fn find_ide_home(config_dir: &Path) -> Option<PathBuf> {
+    let product = config_dir.file_name()?;
+    let candidates = [
+        Some(config_dir.join(".home")),
+        dirs::cache_dir().map(|p| p.join("JetBrains").join(product).join(".home")),
+        dirs::data_local_dir().map(|p| p.join("JetBrains").join(product).join(".home")),
+    ];
+
+    candidates.into_iter().flatten().find_map(|home_file| {
+        let home = fs::read_to_string(home_file).ok()?;
+        let home = PathBuf::from(home.trim());
+        if home.is_dir() { Some(home) } else { None }
+    })
+}
+
+/// 在安装目录中查找可执行的启动器
+fn find_launcher_in_home(home: &Path, script: &str) -> Option<PathBuf> {
+    let candidates: Vec<PathBuf> = if cfg!(target_os = "windows") {
+        vec![
+            home.join("bin").join(format!("{}64.exe", script)),
+            home.join("bin").join(format!("{}.exe", script)),
+            home.join("bin").join(format!("{}.bat", script)),
+        ]
+    } else if cfg!(target_os = "macos") {
+        vec![
+            home.join("Contents").join("MacOS").join(script),
+            home.join("bin").join(format!("{}.sh", script)),
+        ]
+    } else {
+        vec![
+            home.join("bin").join(script),
+            home.join("bin").join(format!("{}.sh", script)),
+        ]
+    };
+    candidates.into_iter().find(|p| p.is_file())
+}
+
+/// 在 PATH 中查找 `idea`、`pycharm` 等命令行启动脚本（如 Toolbox 生成的脚本）
+fn find_launcher_in_path(script: &str) -> Option<PathBuf> {
+    let extensions: &[&str] = if cfg!(target_os = "windows") {
+        &[".cmd", ".bat", ".exe"]
+    } else {
+        &[""]
+    };
+    let path_var = env::var_os("PATH")?;
+    env::split_paths(&path_var).find_map(|dir| {
+        extensions
+            .iter()
+            .map(|ext| dir.join(format!("{}{}", script, ext)))
+            .find(|p| p.is_file())
+    })
+}
+
+/// 把命令模板拆分为参数，支持双引号包裹含空格的路径，并替换 {file} / {line}
+fn expand_command_template(template: &str, file_path: &str, line_number: i32) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in template.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        args.push(current);
+    }
+
+    args.into_iter()
+        .map(|a| a.replace("{file}", file_path).replace("{line}", &line_number.to_string()))
+        .collect()
+}
+
+fn spawn(program: &str, args: &[String]) -> Result<(), String> {
+    Command::new(program)
+        .args(args)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to launch {}: {}", program, e))
+}
+
+/// 在 IDE 中打开书签所在文件并跳转到指定行
+/// 依次尝试：自定义命令模板 -> 配置目录对应的安装目录 -> PATH 中的启动脚本 -> 系统默认打开方式
+/// 返回实际使用的启动方式
+#[tauri::command]
+pub fn open_bookmark_in_ide<R: Runtime>(
+    app: AppHandle<R>,
+    file_path: String,
+    line_number: i32,
+    idea_path: Option<String>,
+) -> Result<String, String> {
+    if !Path::new(&file_path).exists() {
+        return Err("File does not exist".to_string());
+    }
+
+    if let Some(template) = load_prefs(&app)?.ide.command_template {
+        let args = expand_command_template(&template, &file_path, line_number);
+        let (program, rest) = args.split_first().ok_or("IDE command template is empty")?;
+        spawn(program, rest)?;
+        return Ok(program.clone());
+    }
+
+    let config_dir = idea_path.map(PathBuf::from);
+    let script = config_dir
+        .as_deref()
+        .and_then(|p| p.file_name())
+        .map(|n| launcher_script_name(&n.to_string_lossy()))
+        .unwrap_or("idea");
+
+    let launcher = config_dir
+        .as_deref()
+        .and_then(find_ide_home)
+        .and_then(|home| find_launcher_in_home(&home, script))
+        .or_else(|| find_launcher_in_path(script));
+
+    if let Some(launcher) = launcher {
+        let program = launcher.to_string_lossy().to_string();
+        println!("Opening {}:{} with {}", file_path, line_number, program);
+        spawn(&program, &["--line".to_string(), line_number.to_string(), file_path])?;
+        return Ok(program);
+    }
+
+    println!("No IDE launcher found, falling back to system opener");
+    app.opener()
+        .open_path(file_path, None::<&str>)
+        .map_err(|e| e.to_string())?;
+    Ok("system".to_string())
+}
@@ -0,0 +1,472 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use chrono::Utc;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, ToSql};
+use tauri::{AppHandle, Runtime};
+
+use crate::backup_integrity::sha256_hex;
+use crate::bookmark_manager::{restore_backup_from_trash, trash_backup};
+use crate::db::{open_db, Bookmark};
+use crate::relocate::{move_bookmark_data, restore_bookmark_data, BookmarkAnchor};
+
+/// 操作历史最多保留的条数，超出后丢弃最早的记录
+const JOURNAL_LIMIT: i64 = 200;
+/// 允许以 TableRows 记录和还原的表，防止被篡改的记录改写其它表
+const JOURNALED_TABLES: &[&str] = &["bookmark_annotations", "tags", "bookmark_tags", "collections", "collection_items", "backups"];
+
+/// 一行数据的快照：列名 -> 值
+pub(crate) type RowSnapshot = BTreeMap<String, serde_json::Value>;
+
+/// 文件内容的引用：payload 中只保存哈希，内容存放在 journal_blobs 表
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(from = "StoredContent")]
+pub(crate) struct FileContent {
+    pub sha256: String,
+    /// 记录时待写入 journal_blobs 的内容，读取历史时为 None
+    #[serde(skip)]
+    bytes: Option<Vec<u8>>,
+}
+
+/// 早期记录直接内嵌文件内容，读取时兼容两种格式
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StoredContent {
+    Inline(String),
+    Reference { sha256: String },
+}
+
+impl From<StoredContent> for FileContent {
+    fn from(stored: StoredContent) -> Self {
+        match stored {
+            StoredContent::Inline(text) => FileContent::new(text.into_bytes()),
+            StoredContent::Reference { sha256 } => FileContent { sha256, bytes: None },
+        }
+    }
+}
+
+impl FileContent {
+    fn new(bytes: Vec<u8>) -> Self {
+        FileContent { sha256: sha256_hex(&bytes), bytes: Some(bytes) }
+    }
+
+    fn load(&self, conn: &Connection) -> Result<Vec<u8>, String> {
+        if let Some(bytes) = &self.bytes {
+            return Ok(bytes.clone());
+        }
+        conn.query_row("SELECT content FROM journal_blobs WHERE sha256 = ?1", params![self.sha256], |row| row.get(0))
+            .optional()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Journal content missing: {}", self.sha256))
+    }
+}
+
+/// 可撤销的操作，撤销时反向执行，重做时正向执行
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum Operation {
+    /// 本地书签行的变化：新增时 before 为空，删除时 after 为空
+    BookmarkRows { before: Vec<Bookmark>, after: Vec<Bookmark> },
+    /// 文件内容的变化，None 表示文件不存在
+    FileEdit { path: String, before: Option<FileContent>, after: Option<FileContent> },
+    /// 备份文件被移入回收区
+    BackupDelete { backup_path: String, trash_path: String, projects: Vec<String> },
+    /// 书签行号变化时随之迁移的本地信息、锚点与合集步骤，anchor 为迁移前的锚点
+    LineMove {
+        project_name: String,
+        file_path: String,
+        old_line: i32,
+        new_line: i32,
+        anchor: Option<BookmarkAnchor>,
+        #[serde(default)]
+        collection_items: Vec<i64>,
+    },
+    /// 其它表中若干行的变化，按 key 列匹配：只在 before 中的行被删除，只在 after 中的行被插入，其余更新
+    TableRows { table: String, key: Vec<String>, before: Vec<RowSnapshot>, after: Vec<RowSnapshot> },
+    /// 一次命令中的多个操作，按顺序执行、逆序撤销
+    Batch { operations: Vec<Operation> },
+}
+
+impl Operation {
+    fn kind(&self) -> &'static str {
+        match self {
+            Operation::BookmarkRows { .. } => "bookmark_rows",
+            Operation::FileEdit { .. } => "file_edit",
+            Operation::BackupDelete { .. } => "backup_delete",
+            Operation::LineMove { .. } => "line_move",
+            Operation::TableRows { .. } => "table_rows",
+            Operation::Batch { .. } => "batch",
+        }
+    }
+
+    fn file_contents(&self) -> Vec<&FileContent> {
+        match self {
+            Operation::FileEdit { before, after, .. } => before.iter().chain(after).collect(),
+            Operation::Batch { operations } => operations.iter().flat_map(Operation::file_contents).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// 操作历史中的一条记录
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct JournalEntry {
+    pub id: i64,
+    pub kind: String,
+    pub summary: String,
+    pub created_at: String,
+    /// 已撤销、可重做
+    pub undone: bool,
+}
+
+/// 在给定连接上记录一次操作；新操作会清空可重做的记录
+pub(crate) fn record_operation(conn: &Connection, summary: &str, op: &Operation) -> Result<(), String> {
+    let payload = serde_json::to_string(op).map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM operation_journal WHERE undone = 1", [])
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO operation_journal (kind, summary, payload, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![op.kind(), summary, payload, Utc::now().to_rfc3339()],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM operation_journal WHERE id <= (SELECT MAX(id) FROM operation_journal) - ?1",
+        params![JOURNAL_LIMIT],
+    )
+    .map_err(|e| e.to_string())?;
+
+    for content in op.file_contents() {
+        if let Some(bytes) = &content.bytes {
+            conn.execute(
+                "INSERT OR IGNORE INTO journal_blobs (sha256, content) VALUES (?1, ?2)",
+                params![content.sha256, bytes],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    // 清理不再被任何记录引用的文件内容
+    conn.execute(
+        "DELETE FROM journal_blobs WHERE NOT EXISTS (SELECT 1 FROM operation_journal WHERE instr(payload, journal_blobs.sha256) > 0)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 记录一次不涉及数据库事务的操作（文件修改、备份删除）
+/// 记录失败只打印日志，不影响已经完成的操作
+pub(crate) fn record<R: Runtime>(app: &AppHandle<R>, summary: &str, op: Operation) {
+    let result = open_db(app).and_then(|conn| record_operation(&conn, summary, &op));
+    if let Err(e) = result {
+        println!("Failed to record operation '{}': {}", summary, e);
+    }
+}
+
+/// 写入文件并返回可撤销的文件修改记录
+pub(crate) fn write_file(path: &Path, content: &str) -> Result<Operation, String> {
+    write_file_bytes(path, content.as_bytes())
+}
+
+/// 按字节写入文件并返回可撤销的文件修改记录，用于非文本文件
+pub(crate) fn write_file_bytes(path: &Path, content: &[u8]) -> Result<Operation, String> {
+    let before = fs::read(path).ok();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, content).map_err(|e| e.to_string())?;
+    Ok(Operation::FileEdit {
+        path: path.to_string_lossy().to_string(),
+        before: before.map(FileContent::new),
+        after: Some(FileContent::new(content.to_vec())),
+    })
+}
+
+/// 把文件从 expected 状态改为 content 状态；文件在记录之后被其它程序修改过时拒绝执行
+fn set_file_content(conn: &Connection, path: &str, expected: &Option<FileContent>, content: &Option<FileContent>) -> Result<(), String> {
+    let path = Path::new(path);
+    let current = fs::read(path).ok().map(|bytes| sha256_hex(&bytes));
+    if current.as_deref() != expected.as_ref().map(|c| c.sha256.as_str()) {
+        return Err(format!("File was modified outside the app since this operation: {}", path.display()));
+    }
+    match content {
+        Some(content) => {
+            let bytes = content.load(conn)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            fs::write(path, bytes).map_err(|e| e.to_string())
+        }
+        None if path.exists() => fs::remove_file(path).map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+fn replace_bookmark_rows(conn: &Connection, remove: &[Bookmark], insert: &[Bookmark]) -> Result<(), String> {
+    for b in remove {
+        conn.execute("DELETE FROM bookmarks WHERE id = ?1", params![b.id])
+            .map_err(|e| e.to_string())?;
+    }
+    for b in insert {
+        conn.execute(
+            "INSERT OR REPLACE INTO bookmarks (id, title, file_path, line_number, content, created_at, project, group_name, mnemonic)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![b.id, b.title, b.file_path, b.line_number, b.content, b.created_at, b.project, b.group_name, b.mnemonic],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn to_json(value: ValueRef) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => f.into(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into(),
+        ValueRef::Blob(b) => b.to_vec().into(),
+    }
+}
+
+fn to_sql(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => n.as_i64().map(Value::Integer).unwrap_or_else(|| Value::Real(n.as_f64().unwrap_or_default())),
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        serde_json::Value::Array(items) => Value::Blob(items.iter().filter_map(|i| i.as_u64()).map(|b| b as u8).collect()),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+fn check_identifiers(table: &str, columns: &[&String]) -> Result<(), String> {
+    if !JOURNALED_TABLES.contains(&table) {
+        return Err(format!("Table {} cannot be restored from the journal", table));
+    }
+    if columns.iter().any(|c| c.is_empty() || !c.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_')) {
+        return Err(format!("Invalid column name in journal entry for {}", table));
+    }
+    Ok(())
+}
+
+/// 读取 table 中满足 filter 条件的行；filter 为 SQL 条件表达式，参数用 ?1、?2 … 传入
+pub(crate) fn snapshot_rows(conn: &Connection, table: &str, filter: &str, args: &[&dyn ToSql]) -> Result<Vec<RowSnapshot>, String> {
+    check_identifiers(table, &[])?;
+    let mut stmt = conn
+        .prepare(&format!("SELECT * FROM {} WHERE {}", table, filter))
+        .map_err(|e| e.to_string())?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let mut rows = stmt.query(args).map_err(|e| e.to_string())?;
+    let mut snapshots = Vec::new();
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let mut snapshot = RowSnapshot::new();
+        for (i, column) in columns.iter().enumerate() {
+            snapshot.insert(column.clone(), to_json(row.get_ref(i).map_err(|e| e.to_string())?));
+        }
+        snapshots.push(snapshot);
+    }
+    Ok(snapshots)
+}
+
+/// 比较修改前后的快照，没有变化时返回 None
+pub(crate) fn table_rows(table: &str, key: &[&str], before: Vec<RowSnapshot>, after: Vec<RowSnapshot>) -> Option<Operation> {
+    (before != after).then(|| Operation::TableRows {
+        table: table.to_string(),
+        key: key.iter().map(|k| k.to_string()).collect(),
+        before,
+        after,
+    })
+}
+
+fn row_key(row: &RowSnapshot, key: &[String]) -> Vec<serde_json::Value> {
+    key.iter().map(|k| row.get(k).cloned().unwrap_or_default()).collect()
+}
+
+/// 把表中的行从 from 状态改为 to 状态
+fn replace_table_rows(conn: &Connection, table: &str, key: &[String], from: &[RowSnapshot], to: &[RowSnapshot]) -> Result<(), String> {
+    let columns: Vec<&String> = key.iter().chain(from.iter().chain(to).flat_map(|r| r.keys())).collect();
+    check_identifiers(table, &columns)?;
+    let key_filter = key
+        .iter()
+        .enumerate()
+        .map(|(i, k)| format!("{} = ?{}", k, i + 1))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+
+    let to_keys: Vec<Vec<serde_json::Value>> = to.iter().map(|r| row_key(r, key)).collect();
+    for row in from.iter().filter(|r| !to_keys.contains(&row_key(r, key))) {
+        let args: Vec<Value> = row_key(row, key).iter().map(to_sql).collect();
+        conn.execute(&format!("DELETE FROM {} WHERE {}", table, key_filter), params_from_iter(args))
+            .map_err(|e| e.to_string())?;
+    }
+
+    let from_keys: Vec<Vec<serde_json::Value>> = from.iter().map(|r| row_key(r, key)).collect();
+    for row in to {
+        let names: Vec<&String> = row.keys().collect();
+        let values: Vec<Value> = row.values().map(to_sql).collect();
+        if from_keys.contains(&row_key(row, key)) {
+            // 已存在的行用 UPDATE 修改，避免 INSERT OR REPLACE 触发外键级联删除
+            let assignments = names
+                .iter()
+                .enumerate()
+                .map(|(i, c)| format!("{} = ?{}", c, i + 1))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let filter = key
+                .iter()
+                .enumerate()
+                .map(|(i, k)| format!("{} = ?{}", k, names.len() + i + 1))
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            let args: Vec<Value> = values.into_iter().chain(row_key(row, key).iter().map(to_sql)).collect();
+            conn.execute(&format!("UPDATE {} SET {} WHERE {}", table, assignments, filter), params_from_iter(args))
+                .map_err(|e| e.to_string())?;
+        } else {
+            let columns = names.iter().map(|c| c.as_str()).collect::<Vec<_>>().join(", ");
+            let placeholders = (1..=names.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ");
+            conn.execute(&format!("INSERT INTO {} ({}) VALUES ({})", table, columns, placeholders), params_from_iter(values))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// 执行操作：reverse 为 true 时撤销，否则重做
+fn apply_operation<R: Runtime>(app: &AppHandle<R>, conn: &Connection, op: &Operation, reverse: bool) -> Result<(), String> {
+    match op {
+        Operation::BookmarkRows { before, after } => {
+            let (remove, insert) = if reverse { (after, before) } else { (before, after) };
+            replace_bookmark_rows(conn, remove, insert)
+        }
+        Operation::FileEdit { path, before, after } => {
+            let (expected, content) = if reverse { (after, before) } else { (before, after) };
+            set_file_content(conn, path, expected, content)
+        }
+        Operation::BackupDelete { backup_path, trash_path, projects } => {
+            if reverse {
+                restore_backup_from_trash(conn, Path::new(trash_path), Path::new(backup_path), projects.clone())
+            } else {
+                trash_backup(app, conn, Path::new(backup_path)).map(|_| ())
+            }
+        }
+        Operation::LineMove { project_name, file_path, old_line, new_line, anchor, collection_items } => {
+            if reverse {
+                restore_bookmark_data(conn, project_name, file_path, *old_line, *new_line, anchor.as_ref(), collection_items)
+            } else {
+                move_bookmark_data(conn, project_name, file_path, *old_line, *new_line).map(|_| ())
+            }
+        }
+        Operation::TableRows { table, key, before, after } => {
+            let (from, to) = if reverse { (after, before) } else { (before, after) };
+            replace_table_rows(conn, table, key, from, to)
+        }
+        Operation::Batch { operations } => {
+            let ordered: Vec<&Operation> = if reverse {
+                operations.iter().rev().collect()
+            } else {
+                operations.iter().collect()
+            };
+            for (i, op) in ordered.iter().enumerate() {
+                if let Err(e) = apply_operation(app, conn, op, reverse) {
+                    // 数据库的修改随事务回滚，这里把已执行部分的文件修改还原
+                    for done in ordered[..i].iter().rev() {
+                        let _ = apply_operation(app, conn, done, !reverse);
+                    }
+                    return Err(e);
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn load_entry(conn: &Connection, sql: &str) -> Result<Option<(JournalEntry, Operation)>, String> {
+    let row: Option<(JournalEntry, String)> = conn
+        .query_row(sql, [], |row| {
+            Ok((
+                JournalEntry {
+                    id: row.get(0)?,
+                    kind: row.get(1)?,
+                    summary: row.get(2)?,
+                    created_at: row.get(4)?,
+                    undone: row.get(5)?,
+                },
+                row.get(3)?,
+            ))
+        })
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    match row {
+        Some((entry, payload)) => {
+            let op = serde_json::from_str(&payload).map_err(|e| e.to_string())?;
+            Ok(Some((entry, op)))
+        }
+        None => Ok(None),
+    }
+}
+
+fn step<R: Runtime>(app: &AppHandle<R>, reverse: bool) -> Result<Option<JournalEntry>, String> {
+    let mut conn = open_db(app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let sql = if reverse {
+        "SELECT id, kind, summary, payload, created_at, undone FROM operation_journal WHERE undone = 0 ORDER BY id DESC LIMIT 1"
+    } else {
+        "SELECT id, kind, summary, payload, created_at, undone FROM operation_journal WHERE undone = 1 ORDER BY id ASC LIMIT 1"
+    };
+    let Some((mut entry, op)) = load_entry(&tx, sql)? else {
+        return Ok(None);
+    };
+
+    apply_operation(app, &tx, &op, reverse)?;
+    tx.execute(
+        "UPDATE operation_journal SET undone = ?1 WHERE id = ?2",
+        params![reverse, entry.id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    entry.undone = reverse;
+    println!("{} operation: {}", if reverse { "Undid" } else { "Redid" }, entry.summary);
+    Ok(Some(entry))
+}
+
+/// 撤销最近一次操作，没有可撤销的操作时返回 None
+#[tauri::command]
+pub fn undo_last_operation<R: Runtime>(app: AppHandle<R>) -> Result<Option<JournalEntry>, String> {
+    step(&app, true)
+}
+
+/// 重做最近一次撤销的操作，没有可重做的操作时返回 None
+#[tauri::command]
+pub fn redo_operation<R: Runtime>(app: AppHandle<R>) -> Result<Option<JournalEntry>, String> {
+    step(&app, false)
+}
+
+/// 获取操作历史，最新的在前
+#[tauri::command]
+pub fn get_operation_history<R: Runtime>(app: AppHandle<R>, limit: Option<i64>) -> Result<Vec<JournalEntry>, String> {
+    let conn = open_db(&app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, kind, summary, created_at, undone FROM operation_journal ORDER BY id DESC LIMIT ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![limit.unwrap_or(JOURNAL_LIMIT)], |row| {
+            Ok(JournalEntry {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                summary: row.get(2)?,
+                created_at: row.get(3)?,
+                undone: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut entries = Vec::new();
+    for entry in rows {
+        entries.push(entry.map_err(|e| e.to_string())?);
+    }
+    Ok(entries)
+}
@@ -1,6 +1,27 @@
 mod db;
 mod bookmark_manager;
 mod preferences;
+mod git_tracker;
+mod ide_launcher;
+mod exporter;
+mod workspace_writer;
+mod vscode_bookmarks;
+mod migration;
+mod path_remap;
+mod annotations;
+mod relocate;
+mod collections;
+mod project_detect;
+mod journal;
+mod backup_integrity;
+mod backup_store;
+mod backup_location;
+mod backup_crypto;
+mod config_snapshot;
+mod stats;
+mod trends;
+mod profiles;
+mod mnemonic;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -8,32 +29,139 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+/// 启动时初始化数据库并整理备份数据，需要在设置可以正常读取之后执行
+fn init_app_data<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<(), Box<dyn std::error::Error>> {
+    db::init_db(app)?;
+    if let Err(e) = backup_store::migrate_backup_meta(app) {
+        println!("Failed to migrate backup metadata: {}", e);
+    }
+    if let Err(e) = bookmark_manager::purge_expired_trash(app) {
+        println!("Failed to purge expired trash: {}", e);
+    }
+    Ok(())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .setup(|app| {
-            db::init_db(app.handle())?;
-            Ok(())
+            // 设置文件无法读取时先让用户选择重置或退出，不带着默认设置继续启动
+            if let Err(e) = preferences::load_settings(app.handle()) {
+                preferences::prompt_settings_recovery(app.handle(), e, |handle| {
+                    if let Err(e) = init_app_data(handle) {
+                        println!("Failed to initialize app data: {}", e);
+                        handle.exit(1);
+                    }
+                });
+                return Ok(());
+            }
+            init_app_data(app.handle())
         })
         .invoke_handler(tauri::generate_handler![
             greet,
             db::get_bookmarks,
             db::add_bookmark,
             db::delete_bookmark,
+            db::get_bookmark,
+            db::list_bookmarks,
+            db::update_bookmark,
+            db::set_bookmark_project,
+            db::delete_bookmarks,
             db::import_bookmarks,
+            project_detect::clear_project_cache,
             bookmark_manager::find_idea_dirs,
             bookmark_manager::list_workspace_files,
             bookmark_manager::backup_bookmark_file,
             bookmark_manager::get_backup_list,
             bookmark_manager::restore_bookmark_file,
             bookmark_manager::delete_backup_file,
+            bookmark_manager::list_trashed_backups,
+            bookmark_manager::restore_trashed_backup,
+            bookmark_manager::empty_backup_trash,
+            bookmark_manager::set_backup_label,
+            bookmark_manager::set_backup_note,
+            bookmark_manager::set_backup_pinned,
+            bookmark_manager::prune_backups,
+            backup_integrity::verify_backups,
+            backup_location::get_backup_location,
+            backup_location::set_backup_location,
+            backup_location::scan_foreign_backups,
+            backup_crypto::get_backup_encryption_status,
+            backup_crypto::enable_backup_encryption,
+            backup_crypto::disable_backup_encryption,
+            backup_crypto::unlock_backups,
+            backup_crypto::lock_backups,
+            config_snapshot::preview_config_snapshot,
+            config_snapshot::create_config_snapshot,
+            config_snapshot::list_config_snapshots,
+            config_snapshot::restore_config_snapshot,
+            config_snapshot::delete_config_snapshot,
             bookmark_manager::read_bookmarks_from_workspace,
             bookmark_manager::read_backup_bookmarks,
             bookmark_manager::check_idea_running,
+            preferences::get_settings,
+            preferences::reset_corrupt_settings,
+            preferences::get_setting,
+            preferences::set_setting,
+            preferences::reset_settings,
+            profiles::list_profiles,
+            profiles::create_profile,
+            profiles::clone_profile,
+            profiles::switch_profile,
+            profiles::delete_profile,
             preferences::get_saved_idea_version,
             preferences::save_idea_version,
+            preferences::get_ide_command_template,
+            preferences::save_ide_command_template,
+            preferences::get_path_remap_rules,
+            preferences::save_path_remap_rules,
+            preferences::get_trash_retention_days,
+            preferences::save_trash_retention_days,
+            relocate::capture_bookmark_anchor,
+            relocate::relocate_bookmark,
+            relocate::apply_relocation_to_workspace,
+            relocate::apply_relocation_to_db,
+            git_tracker::record_bookmark_commit,
+            git_tracker::map_bookmark_line_to_head,
+            git_tracker::get_bookmark_blame,
+            ide_launcher::open_bookmark_in_ide,
+            exporter::export_bookmarks,
+            vscode_bookmarks::read_vscode_bookmarks,
+            vscode_bookmarks::import_vscode_bookmarks_to_idea,
+            vscode_bookmarks::export_idea_bookmarks_to_vscode,
+            migration::migrate_bookmarks,
+            path_remap::backup_bookmark_file_portable,
+            path_remap::preview_path_remap,
+            path_remap::restore_bookmark_file_remapped,
+            annotations::list_annotated_bookmarks,
+            annotations::set_bookmark_note,
+            annotations::set_bookmark_color,
+            annotations::set_bookmark_pinned,
+            annotations::set_bookmark_favorite,
+            annotations::add_bookmark_tag,
+            annotations::remove_bookmark_tag,
+            annotations::list_tags,
+            annotations::rename_tag,
+            annotations::delete_tag,
+            collections::create_collection,
+            collections::list_collections,
+            collections::get_collection,
+            collections::update_collection,
+            collections::delete_collection,
+            collections::add_collection_item,
+            collections::remove_collection_item,
+            collections::update_collection_step_note,
+            collections::reorder_collection_items,
+            collections::export_collection_walkthrough,
+            stats::get_bookmark_stats,
+            trends::get_bookmark_trends,
+            mnemonic::check_mnemonics,
+            mnemonic::reassign_mnemonics,
+            journal::undo_last_operation,
+            journal::redo_operation,
+            journal::get_operation_history,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Runtime};
+
+use crate::bookmark_manager::{
+    backup_bookmark_file, collect_bookmark_sources, get_recent_projects,
+    parse_bookmarks_from_global_workspace, parse_bookmarks_from_project_idea, ParsedBookmark,
+};
+use crate::journal::{self, Operation};
+use crate::mnemonic::{bookmarks_with_mnemonic, resolve_mnemonic_conflicts};
+use crate::workspace_writer::{
+    append_legacy_bookmarks, append_to_group_state, escape_xml_attr, render_group_state,
+    render_legacy_bookmarks, upsert_group_state,
+};
+
+/// 单个项目的迁移结果
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct MigrationItem {
+    pub project_name: String,
+    pub source_file: String,
+    pub target_file: Option<String>,
+    /// 来源中的书签数量
+    pub bookmark_count: usize,
+    /// 目标中尚不存在、需要写入的书签数量
+    pub new_bookmarks: usize,
+    /// migrated / pending（仅预览）/ skipped
+    pub status: String,
+    pub reason: Option<String>,
+}
+
+/// 迁移报告
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct MigrationReport {
+    pub source_workspace: String,
+    pub target_workspace: String,
+    /// 目标版本使用的书签格式：global（IDEA 2025+）/ legacy（项目 .idea/workspace.xml）
+    pub target_format: String,
+    pub dry_run: bool,
+    pub total_bookmarks: usize,
+    pub migrated_bookmarks: usize,
+    pub items: Vec<MigrationItem>,
+}
+
+/// 待写入某个目标文件的一个项目
+struct PlannedGroup {
+    item_index: usize,
+    project_name: String,
+    project_root: Option<String>,
+    bookmarks: Vec<ParsedBookmark>,
+}
+
+/// 判断目标版本是否使用全局 workspace 目录存储书签 (IDEA 2025+)
+fn target_uses_global_format(target_ws: &Path) -> bool {
+    if let Ok(entries) = fs::read_dir(target_ws) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "xml") {
+                if let Ok(content) = fs::read_to_string(&path) {
+                    if content.contains("BookmarksManager") {
+                        return true;
+                    }
+                }
+            }
+        }
+    }
+
+    // 目录中还没有书签时，根据版本号判断（如 IntelliJIdea2025.1）
+    let version_name = target_ws
+        .parent()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let digits: String = version_name
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse::<u32>().map_or(true, |year| year >= 2025)
+}
+
+/// 在目标 workspace 目录中查找已包含该项目分组的文件
+fn find_group_file(target_ws: &Path, project_name: &str) -> Option<PathBuf> {
+    let marker = format!("<option name=\"name\" value=\"{}\"", escape_xml_attr(project_name));
+    fs::read_dir(target_ws).ok()?.flatten().map(|e| e.path()).find(|path| {
+        path.extension().is_some_and(|ext| ext == "xml")
+            && fs::read_to_string(path).is_ok_and(|c| c.contains(&marker))
+    })
+}
+
+/// 在目标版本的最近项目列表中按目录名查找项目根目录
+fn find_recent_project(target_config: &Path, project_name: &str) -> Option<PathBuf> {
+    get_recent_projects(&target_config.join("options"))
+        .into_iter()
+        .find(|p| p.file_name().is_some_and(|n| n.to_string_lossy() == project_name))
+}
+
+/// 读取目标文件中该项目已有的书签
+fn existing_target_bookmarks(target_file: &Path, project_name: &str, global: bool) -> Vec<ParsedBookmark> {
+    if !target_file.exists() {
+        return Vec::new();
+    }
+    if global {
+        parse_bookmarks_from_global_workspace(target_file)
+            .into_iter()
+            .filter(|b| b.project_name == project_name)
+            .collect()
+    } else {
+        parse_bookmarks_from_project_idea(target_file, project_name)
+    }
+}
+
+/// 将一个 IDEA 版本中的书签迁移到另一个版本，并按目标版本的原生格式写入
+/// dry_run 为 true 时只生成报告，不修改任何文件；写入前会先备份被修改的目标文件
+#[tauri::command]
+pub fn migrate_bookmarks<R: Runtime>(
+    app: AppHandle<R>,
+    source_workspace_path: String,
+    target_workspace_path: String,
+    dry_run: bool,
+) -> Result<MigrationReport, String> {
+    let source_ws = Path::new(&source_workspace_path);
+    let target_ws = Path::new(&target_workspace_path);
+    let target_config = target_ws.parent().ok_or("Cannot find target config dir")?;
+    let global = target_uses_global_format(target_ws);
+
+    let sources = collect_bookmark_sources(source_ws)?;
+    let mut items = Vec::new();
+    let mut plan: BTreeMap<PathBuf, Vec<PlannedGroup>> = BTreeMap::new();
+
+    for source in &sources {
+        let mut by_project: BTreeMap<&str, Vec<ParsedBookmark>> = BTreeMap::new();
+        for b in &source.bookmarks {
+            by_project.entry(b.project_name.as_str()).or_default().push(b.clone());
+        }
+
+        for (project_name, bookmarks) in by_project {
+            let project_root = source
+                .project_root
+                .clone()
+                .or_else(|| find_recent_project(target_config, project_name));
+
+            // 新格式：workspace 文件名是项目 ID，在各版本之间保持不变
+            let target_file = if global {
+                if source.legacy {
+                    find_group_file(target_ws, project_name)
+                } else {
+                    source.file.file_name().map(|n| target_ws.join(n))
+                }
+            } else {
+                project_root.as_ref().map(|r| r.join(".idea").join("workspace.xml"))
+            };
+
+            let mut item = MigrationItem {
+                project_name: project_name.to_string(),
+                source_file: source.file.to_string_lossy().to_string(),
+                target_file: target_file.as_ref().map(|p| p.to_string_lossy().to_string()),
+                bookmark_count: bookmarks.len(),
+                new_bookmarks: 0,
+                status: "skipped".to_string(),
+                reason: None,
+            };
+
+            let Some(target_file) = target_file else {
+                item.reason = Some(if global {
+                    "目标版本中未找到该项目的 workspace 文件，请先用新版本打开一次该项目".to_string()
+                } else {
+                    "无法确定项目根目录".to_string()
+                });
+                items.push(item);
+                continue;
+            };
+
+            let existing = existing_target_bookmarks(&target_file, project_name, global);
+            let new_bookmarks: Vec<ParsedBookmark> = bookmarks
+                .into_iter()
+                .filter(|b| {
+                    !existing
+                        .iter()
+                        .any(|e| e.file_path == b.file_path && e.line_number == b.line_number)
+                })
+                .collect();
+
+            item.new_bookmarks = new_bookmarks.len();
+            if new_bookmarks.is_empty() {
+                item.reason = Some("目标中已存在全部书签".to_string());
+                items.push(item);
+                continue;
+            }
+
+            item.status = if dry_run { "pending" } else { "migrated" }.to_string();
+            plan.entry(target_file).or_default().push(PlannedGroup {
+                item_index: items.len(),
+                project_name: project_name.to_string(),
+                project_root: project_root.map(|p| p.to_string_lossy().to_string()),
+                bookmarks: new_bookmarks,
+            });
+            items.push(item);
+        }
+    }
+
+    if !dry_run {
+        let mut edits = Vec::new();
+        for (target_file, groups) in &plan {
+            match write_target_file(&app, target_file, groups, global) {
+                Ok(op) => edits.push(op),
+                Err(e) => {
+                    for g in groups {
+                        items[g.item_index].status = "skipped".to_string();
+                        items[g.item_index].reason = Some(e.clone());
+                    }
+                }
+            }
+        }
+        if !edits.is_empty() {
+            journal::record(&app, "跨版本迁移书签", Operation::Batch { operations: edits });
+        }
+    }
+
+    let total_bookmarks = items.iter().map(|i| i.bookmark_count).sum();
+    let migrated_bookmarks = items
+        .iter()
+        .filter(|i| i.status != "skipped")
+        .map(|i| i.new_bookmarks)
+        .sum();
+
+    Ok(MigrationReport {
+        source_workspace: source_workspace_path,
+        target_workspace: target_workspace_path,
+        target_format: if global { "global" } else { "legacy" }.to_string(),
+        dry_run,
+        total_bookmarks,
+        migrated_bookmarks,
+        items,
+    })
+}
+
+fn write_target_file<R: Runtime>(
+    app: &AppHandle<R>,
+    target_file: &Path,
+    groups: &[PlannedGroup],
+    global: bool,
+) -> Result<Operation, String> {
+    let mut content = if target_file.exists() {
+        let projects = groups.iter().map(|g| g.project_name.clone()).collect();
+        backup_bookmark_file(app.clone(), target_file.to_string_lossy().to_string(), projects)?;
+        fs::read_to_string(target_file).map_err(|e| e.to_string())?
+    } else {
+        String::new()
+    };
+    let keep = bookmarks_with_mnemonic(&content);
+
+    for g in groups {
+        let root = g.project_root.as_deref();
+        content = if !global {
+            append_legacy_bookmarks(&content, &render_legacy_bookmarks(&g.bookmarks, root))
+        } else {
+            // 已有同名分组时追加书签以保留分组的其它选项，否则新建分组
+            append_to_group_state(&content, &g.project_name, &g.bookmarks, root).unwrap_or_else(|| {
+                upsert_group_state(&content, &g.project_name, &render_group_state(&g.project_name, &g.bookmarks, root))
+            })
+        };
+    }
+
+    // 迁移来的助记符与目标中已有的重复时 IDEA 会丢弃其一，已有书签保留原助记符，其余改用空闲助记符
+    let (content, changes) = resolve_mnemonic_conflicts(&content, &keep);
+    for c in &changes {
+        println!("Reassigned mnemonic {:?} -> {:?} for {}:{}", c.from, c.to, c.file_path, c.line_number);
+    }
+
+    let op = journal::write_file(target_file, &content)?;
+    println!("Migrated {} groups into {:?}", groups.len(), target_file);
+    Ok(op)
+}
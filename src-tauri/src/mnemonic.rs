@@ -0,0 +1,402 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Runtime};
+
+use crate::backup_integrity::parse_backup_content;
+use crate::bookmark_manager::{
+    attr_value_range, backup_bookmark_file, bookmark_type_to_mnemonic, clean_bookmark_path, collect_bookmark_sources,
+    extract_attr_value, mnemonic_to_bookmark_type, ParsedBookmark,
+};
+use crate::journal;
+
+/// IDEA 支持的全部助记符，每个在同一项目中只能使用一次
+const MNEMONICS: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// 使用同一助记符的多个书签
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct MnemonicConflict {
+    pub mnemonic: String,
+    /// 按文件中的顺序排列，IDEA 只保留其中一个
+    pub bookmarks: Vec<ParsedBookmark>,
+}
+
+/// 单个 workspace 文件的助记符使用情况
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct MnemonicReport {
+    pub file: String,
+    pub used: Vec<String>,
+    pub free: Vec<String>,
+    pub conflicts: Vec<MnemonicConflict>,
+    /// 助记符不合法（不是单个数字或字母）的书签
+    pub invalid: Vec<ParsedBookmark>,
+}
+
+/// 手动指定的助记符，mnemonic 为空表示改为匿名书签
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct MnemonicAssignment {
+    pub file_path: String,
+    pub line_number: i32,
+    pub mnemonic: Option<String>,
+}
+
+/// 一个书签的助记符变化
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct MnemonicChange {
+    pub file_path: String,
+    pub line_number: i32,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// 重新分配助记符的结果
+#[derive(serde::Serialize, Debug)]
+pub struct MnemonicReassignResult {
+    pub changes: Vec<MnemonicChange>,
+    /// 写入后（或预览时写入后将会）的助记符使用情况
+    pub report: MnemonicReport,
+    pub dry_run: bool,
+}
+
+/// 书签在 XML 文本中的位置及其当前助记符
+struct LocatedBookmark {
+    start: usize,
+    end: usize,
+    legacy: bool,
+    file_path: String,
+    line_number: i32,
+    mnemonic: Option<String>,
+}
+
+/// 合法的助记符统一为大写，非法时返回 None
+fn normalize_mnemonic(mnemonic: &str) -> Option<String> {
+    mnemonic_to_bookmark_type(mnemonic).and_then(|t| bookmark_type_to_mnemonic(&t))
+}
+
+/// 按文件中的顺序定位所有书签，与解析函数使用相同的路径和行号规则
+fn locate_bookmarks(content: &str) -> Vec<LocatedBookmark> {
+    let mut located = Vec::new();
+
+    if content.contains("BookmarksManager") {
+        let mut offset = 0;
+        while let Some(rel) = content[offset..].find("<BookmarkState>") {
+            let start = offset + rel;
+            let Some(end_rel) = content[start..].find("</BookmarkState>") else {
+                break;
+            };
+            let end = start + end_rel + "</BookmarkState>".len();
+            offset = end;
+
+            let chunk = &content[start..end];
+            let Some(url) = chunk.find("key=\"url\"").and_then(|i| extract_attr_value(&chunk[i..], "value")) else {
+                continue;
+            };
+            let line_number = chunk
+                .find("key=\"line\"")
+                .and_then(|i| extract_attr_value(&chunk[i..], "value"))
+                .and_then(|s| s.parse::<i32>().ok())
+                .map(|l| l + 1)
+                .unwrap_or(1);
+            let mnemonic = if let Some(i) = chunk.find("key=\"mnemonic\"") {
+                extract_attr_value(&chunk[i..], "value").filter(|s| !s.is_empty())
+            } else {
+                chunk
+                    .find("name=\"type\"")
+                    .and_then(|i| extract_attr_value(&chunk[i..], "value"))
+                    .and_then(|t| bookmark_type_to_mnemonic(&t))
+            };
+            located.push(LocatedBookmark {
+                start,
+                end,
+                legacy: false,
+                file_path: clean_bookmark_path(&url, None),
+                line_number,
+                mnemonic,
+            });
+        }
+    } else {
+        let mut offset = 0;
+        while let Some(rel) = content[offset..].find("<bookmark") {
+            let start = offset + rel;
+            let Some(end_rel) = content[start..].find('>') else {
+                break;
+            };
+            let end = start + end_rel + 1;
+            offset = end;
+
+            // 跳过 <bookmarks> 等名称以 bookmark 开头的其它元素
+            if !content[start + "<bookmark".len()..].starts_with(char::is_whitespace) {
+                continue;
+            }
+            let tag = &content[start..end];
+            let Some(url) = extract_attr_value(tag, "url") else {
+                continue;
+            };
+            let line_number = extract_attr_value(tag, "line")
+                .and_then(|s| s.parse::<i32>().ok())
+                .map(|l| l + 1)
+                .unwrap_or(1);
+            located.push(LocatedBookmark {
+                start,
+                end,
+                legacy: true,
+                file_path: clean_bookmark_path(&url, None),
+                line_number,
+                mnemonic: extract_attr_value(tag, "mnemonic").filter(|s| !s.is_empty()),
+            });
+        }
+    }
+    located
+}
+
+/// 删除包含 marker 的自闭合元素，连同所在行的缩进与换行
+fn remove_element(chunk: &str, marker: &str) -> String {
+    let Some(idx) = chunk.find(marker) else {
+        return chunk.to_string();
+    };
+    let Some(elem_start) = chunk[..=idx].rfind('<') else {
+        return chunk.to_string();
+    };
+    let Some(close_rel) = chunk[idx..].find("/>") else {
+        return chunk.to_string();
+    };
+    let mut start = elem_start;
+    let mut end = idx + close_rel + 2;
+
+    let line_start = chunk[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    if chunk[line_start..start].trim().is_empty() && chunk[end..].starts_with('\n') {
+        start = line_start;
+        end += 1;
+    }
+    format!("{}{}", &chunk[..start], &chunk[end..])
+}
+
+/// 改写单个书签元素中的助记符
+fn set_chunk_mnemonic(chunk: &str, legacy: bool, mnemonic: Option<&str>) -> String {
+    if legacy {
+        return match (attr_value_range(chunk, "mnemonic"), mnemonic) {
+            (Some((start, end)), Some(m)) => format!("{}{}{}", &chunk[..start], m, &chunk[end..]),
+            (Some((start, end)), None) => {
+                // 连同前面的空格与 mnemonic=" 一起删除
+                let attr_start = chunk[..start].rfind("mnemonic").unwrap_or(start);
+                let attr_start = chunk[..attr_start].trim_end().len();
+                format!("{}{}", &chunk[..attr_start], &chunk[end + 1..])
+            }
+            (None, Some(m)) => {
+                let insert_at = if chunk.ends_with("/>") { chunk.len() - 2 } else { chunk.len() - 1 };
+                let head = chunk[..insert_at].trim_end();
+                format!("{} mnemonic=\"{}\" {}", head, m, &chunk[insert_at..])
+            }
+            (None, None) => chunk.to_string(),
+        };
+    }
+
+    let mut chunk = remove_element(chunk, "key=\"mnemonic\"");
+    chunk = remove_element(&chunk, "<option name=\"type\"");
+    if let Some(bookmark_type) = mnemonic.and_then(mnemonic_to_bookmark_type) {
+        if let Some(close) = chunk.rfind("</BookmarkState>") {
+            // 与 </BookmarkState> 同一行的缩进再多两个空格，和 IDEA 写出的格式一致
+            let line_start = chunk[..close].rfind('\n').map(|i| i + 1).unwrap_or(close);
+            let indent = &chunk[line_start..close];
+            let option = if indent.trim().is_empty() {
+                format!("{}  <option name=\"type\" value=\"{}\" />\n", indent, bookmark_type)
+            } else {
+                format!("<option name=\"type\" value=\"{}\" />", bookmark_type)
+            };
+            let insert_at = if indent.trim().is_empty() { line_start } else { close };
+            chunk.insert_str(insert_at, &option);
+        }
+    }
+    chunk
+}
+
+/// 按计划改写文件内容，返回新内容与变化列表
+fn apply_mnemonics(content: &str, located: &[LocatedBookmark], planned: &[Option<String>]) -> (String, Vec<MnemonicChange>) {
+    let mut result = content.to_string();
+    let mut changes = Vec::new();
+    // 从后往前改写，前面书签的位置不受影响
+    for (b, to) in located.iter().zip(planned).rev() {
+        if b.mnemonic == *to {
+            continue;
+        }
+        let chunk = set_chunk_mnemonic(&result[b.start..b.end], b.legacy, to.as_deref());
+        result.replace_range(b.start..b.end, &chunk);
+        changes.push(MnemonicChange {
+            file_path: b.file_path.clone(),
+            line_number: b.line_number,
+            from: b.mnemonic.clone(),
+            to: to.clone(),
+        });
+    }
+    changes.reverse();
+    (result, changes)
+}
+
+/// 计算每个书签最终的助记符
+/// 冲突时依次保留手动指定的、keep 中的、文件中靠前的书签；auto 为 true 时其余书签改用空闲助记符，用完后改为匿名书签
+fn plan_mnemonics(
+    located: &[LocatedBookmark],
+    assignments: &[MnemonicAssignment],
+    auto: bool,
+    keep: &[(String, i32)],
+) -> Result<Vec<Option<String>>, String> {
+    let mut planned: Vec<Option<String>> = located.iter().map(|b| b.mnemonic.clone()).collect();
+    let mut manual = vec![false; located.len()];
+
+    for a in assignments {
+        let index = located
+            .iter()
+            .position(|b| b.file_path == a.file_path && b.line_number == a.line_number)
+            .ok_or(format!("未找到书签：{}:{}", a.file_path, a.line_number))?;
+        let mnemonic = match a.mnemonic.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+            Some(m) => Some(normalize_mnemonic(m).ok_or(format!("无效的助记符：{}", m))?),
+            None => None,
+        };
+        if let Some(m) = &mnemonic {
+            if planned.iter().zip(&manual).any(|(p, is_manual)| *is_manual && p.as_ref() == Some(m)) {
+                return Err(format!("助记符 {} 被指定给了多个书签", m));
+            }
+        }
+        planned[index] = mnemonic;
+        manual[index] = true;
+    }
+
+    let mut holders: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, m) in planned.iter().enumerate() {
+        if let Some(m) = m.as_deref().and_then(normalize_mnemonic) {
+            holders.entry(m).or_default().push(i);
+        }
+    }
+
+    let mut losers = Vec::new();
+    for (m, indices) in &holders {
+        if indices.len() < 2 {
+            continue;
+        }
+        let kept = located_key_index(indices, &manual, located, keep);
+        for &i in indices.iter().filter(|&&i| i != kept) {
+            if !auto && manual[kept] {
+                let b = &located[i];
+                return Err(format!(
+                    "助记符 {} 已被 {}:{} 使用，请同时为其指定新的助记符或开启自动分配",
+                    m, b.file_path, b.line_number
+                ));
+            }
+            losers.push(i);
+        }
+    }
+
+    if auto {
+        losers.sort_unstable();
+        let mut free = MNEMONICS.chars().map(String::from).filter(|m| !holders.contains_key(m));
+        for i in losers {
+            planned[i] = free.next();
+        }
+    }
+    Ok(planned)
+}
+
+/// 冲突中保留助记符的书签：手动指定 > keep 中的 > 文件中最靠前的
+fn located_key_index(indices: &[usize], manual: &[bool], located: &[LocatedBookmark], keep: &[(String, i32)]) -> usize {
+    indices
+        .iter()
+        .copied()
+        .find(|&i| manual[i])
+        .or_else(|| {
+            indices.iter().copied().find(|&i| {
+                keep.iter().any(|(path, line)| *path == located[i].file_path && *line == located[i].line_number)
+            })
+        })
+        .unwrap_or(indices[0])
+}
+
+/// 统计一组书签的助记符使用情况
+pub(crate) fn analyze_mnemonics(file: &str, bookmarks: &[ParsedBookmark]) -> MnemonicReport {
+    let mut holders: BTreeMap<String, Vec<ParsedBookmark>> = BTreeMap::new();
+    let mut invalid = Vec::new();
+    for b in bookmarks {
+        let Some(raw) = b.mnemonic.as_deref() else {
+            continue;
+        };
+        match normalize_mnemonic(raw) {
+            Some(m) => holders.entry(m).or_default().push(b.clone()),
+            None => invalid.push(b.clone()),
+        }
+    }
+
+    let mut used: Vec<String> = holders.keys().cloned().collect();
+    // 数字排在字母前，与 IDEA 中的顺序一致
+    used.sort_by_key(|m| MNEMONICS.find(m.as_str()));
+    let free = MNEMONICS.chars().map(String::from).filter(|m| !holders.contains_key(m)).collect();
+    let mut conflicts: Vec<MnemonicConflict> = holders
+        .into_iter()
+        .filter(|(_, b)| b.len() > 1)
+        .map(|(mnemonic, bookmarks)| MnemonicConflict { mnemonic, bookmarks })
+        .collect();
+    conflicts.sort_by_key(|c| MNEMONICS.find(c.mnemonic.as_str()));
+
+    MnemonicReport { file: file.to_string(), used, free, conflicts, invalid }
+}
+
+/// 自动解决冲突：keep 中的书签优先保留助记符，用于写回书签时避免 IDEA 丢弃重复的助记符
+pub(crate) fn resolve_mnemonic_conflicts(content: &str, keep: &[(String, i32)]) -> (String, Vec<MnemonicChange>) {
+    let located = locate_bookmarks(content);
+    match plan_mnemonics(&located, &[], true, keep) {
+        Ok(planned) => apply_mnemonics(content, &located, &planned),
+        Err(_) => (content.to_string(), Vec::new()),
+    }
+}
+
+/// 内容中已有助记符的书签 (路径, 行号)，作为 resolve_mnemonic_conflicts 的 keep 参数
+pub(crate) fn bookmarks_with_mnemonic(content: &str) -> Vec<(String, i32)> {
+    locate_bookmarks(content)
+        .into_iter()
+        .filter(|b| b.mnemonic.is_some())
+        .map(|b| (b.file_path, b.line_number))
+        .collect()
+}
+
+/// 检查 workspace 中每个书签文件的助记符：冲突、空闲与不合法的助记符
+#[tauri::command]
+pub fn check_mnemonics(workspace_path: String) -> Result<Vec<MnemonicReport>, String> {
+    let sources = collect_bookmark_sources(Path::new(&workspace_path))?;
+    Ok(sources
+        .iter()
+        .map(|s| analyze_mnemonics(&s.file.to_string_lossy(), &s.bookmarks))
+        .collect())
+}
+
+/// 重新分配单个书签文件中的助记符
+/// assignments 为手动指定的助记符；auto_resolve 为 true 时自动为其余冲突的书签分配空闲助记符
+/// 写入前会先备份原文件，修改可以撤销
+#[tauri::command]
+pub fn reassign_mnemonics<R: Runtime>(
+    app: AppHandle<R>,
+    file_path: String,
+    assignments: Vec<MnemonicAssignment>,
+    auto_resolve: bool,
+    dry_run: bool,
+) -> Result<MnemonicReassignResult, String> {
+    let path = Path::new(&file_path);
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let located = locate_bookmarks(&content);
+    let planned = plan_mnemonics(&located, &assignments, auto_resolve, &[])?;
+    let (new_content, changes) = apply_mnemonics(&content, &located, &planned);
+
+    if !dry_run && !changes.is_empty() {
+        let bookmarks = parse_backup_content(&content);
+        let projects: BTreeSet<String> = bookmarks.iter().map(|b| b.project_name.clone()).filter(|p| !p.is_empty()).collect();
+        backup_bookmark_file(app.clone(), file_path.clone(), projects.into_iter().collect())?;
+
+        let op = journal::write_file(path, &new_content)?;
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        journal::record(&app, &format!("重新分配 {} 中的 {} 个助记符", file_name, changes.len()), op);
+        println!("Reassigned {} mnemonics in {:?}", changes.len(), path);
+    }
+
+    Ok(MnemonicReassignResult {
+        report: analyze_mnemonics(&file_path, &parse_backup_content(&new_content)),
+        changes,
+        dry_run,
+    })
+}
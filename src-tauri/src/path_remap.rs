@@ -0,0 +1,222 @@
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Runtime};
+
+use crate::backup_crypto::read_backup_content;
+use crate::bookmark_manager::{clean_bookmark_path, create_backup, get_recent_projects};
+use crate::journal;
+use crate::preferences::load_prefs;
+use crate::workspace_writer::{bookmark_url, normalize_path, relativize_path};
+
+/// 路径前缀映射规则，如 `C:/Users/alice/work` -> `/home/bob/work`
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct PathRemapRule {
+    pub from: String,
+    pub to: String,
+}
+
+/// 单个书签路径的映射预览
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct RemapPreview {
+    /// 备份中的原始 URL
+    pub original_url: String,
+    /// 应用规则后的 URL
+    pub remapped_url: String,
+    /// 解析到本机的文件路径，$PROJECT_DIR$ 无法解析时为空
+    pub resolved_path: Option<String>,
+    /// 解析后的文件在本机是否存在
+    pub resolves: bool,
+    /// 是否被某条规则改写
+    pub changed: bool,
+}
+
+/// 遍历 XML 中所有 "file://..." 形式的属性值，用回调返回的新 URL 替换，返回 (新内容, 替换次数)
+fn rewrite_file_urls(content: &str, mut rewrite: impl FnMut(&str) -> Option<String>) -> (String, usize) {
+    let mut out = String::with_capacity(content.len());
+    let mut count = 0;
+    let mut rest = content;
+
+    while let Some(idx) = rest.find("\"file://") {
+        let url_start = idx + 1;
+        let Some(len) = rest[url_start..].find('"') else { break };
+        let url = &rest[url_start..url_start + len];
+
+        out.push_str(&rest[..url_start]);
+        match rewrite(url) {
+            Some(new_url) if new_url != url => {
+                out.push_str(&new_url);
+                count += 1;
+            }
+            _ => out.push_str(url),
+        }
+        rest = &rest[url_start + len..];
+    }
+    out.push_str(rest);
+    (out, count)
+}
+
+/// 列出 XML 中的所有 file:// URL
+fn collect_file_urls(content: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    rewrite_file_urls(content, |url| {
+        if !urls.iter().any(|u| u == url) {
+            urls.push(url.to_string());
+        }
+        None
+    });
+    urls
+}
+
+/// 把位于项目根目录下的绝对路径改写为 $PROJECT_DIR$ 相对形式，返回 (新内容, 改写数量)
+pub(crate) fn make_portable(content: &str, project_roots: &[String]) -> (String, usize) {
+    // 优先匹配最长的根目录，避免嵌套项目被外层项目截获
+    let mut roots: Vec<&String> = project_roots.iter().collect();
+    roots.sort_by_key(|r| std::cmp::Reverse(normalize_path(r).len()));
+
+    rewrite_file_urls(content, |url| {
+        if url.contains("$PROJECT_DIR$") {
+            return None;
+        }
+        let path = clean_bookmark_path(url, None);
+        roots
+            .iter()
+            .find_map(|root| relativize_path(&path, root))
+            .map(|rel| format!("file://$PROJECT_DIR$/{}", rel))
+    })
+}
+
+/// 对单个路径应用前缀映射规则（第一条匹配的规则生效）
+fn remap_path(path: &str, rules: &[PathRemapRule]) -> Option<String> {
+    let path = normalize_path(path);
+    rules.iter().find_map(|rule| {
+        let from = normalize_path(&rule.from);
+        if from.is_empty() {
+            return None;
+        }
+        // 带盘符的 Windows 前缀不区分大小写
+        let prefix = path.get(..from.len())?;
+        let matches = if from.contains(':') {
+            prefix.eq_ignore_ascii_case(&from)
+        } else {
+            prefix == from
+        };
+        if !matches {
+            return None;
+        }
+        let rest = &path[from.len()..];
+        if !rest.is_empty() && !rest.starts_with('/') {
+            // 只匹配完整的目录名：/work 不应匹配 /workspace
+            return None;
+        }
+        Some(format!("{}{}", normalize_path(&rule.to), rest))
+    })
+}
+
+/// 对 XML 中的绝对路径 URL 应用映射规则，返回 (新内容, 改写数量)
+pub(crate) fn apply_remap_rules(content: &str, rules: &[PathRemapRule]) -> (String, usize) {
+    rewrite_file_urls(content, |url| {
+        if url.contains("$PROJECT_DIR$") {
+            return None;
+        }
+        remap_path(&clean_bookmark_path(url, None), rules).map(|p| bookmark_url(&p, None))
+    })
+}
+
+/// 以可移植模式备份：项目内的绝对路径改写为 $PROJECT_DIR$ 相对形式
+/// 项目根目录取自该 IDEA 版本的最近项目列表，也可额外指定
+#[tauri::command]
+pub fn backup_bookmark_file_portable<R: Runtime>(
+    app: AppHandle<R>,
+    file_path: String,
+    projects: Vec<String>,
+    project_roots: Option<Vec<String>>,
+) -> Result<String, String> {
+    let source_path = Path::new(&file_path);
+    let content = fs::read_to_string(source_path).map_err(|e| e.to_string())?;
+
+    let mut roots = project_roots.unwrap_or_default();
+    // workspace/xxx.xml -> 配置目录/options/recentProjects.xml
+    if let Some(config_dir) = source_path.parent().and_then(|p| p.parent()) {
+        roots.extend(
+            get_recent_projects(&config_dir.join("options"))
+                .into_iter()
+                .map(|p| p.to_string_lossy().to_string()),
+        );
+    }
+
+    let (portable, count) = make_portable(&content, &roots);
+    println!("Portable backup: rewrote {} absolute paths", count);
+
+    let backup_path = create_backup(&app, source_path, portable.as_bytes(), projects)?;
+    Ok(backup_path.to_string_lossy().to_string())
+}
+
+/// 预览映射规则对备份中各书签路径的效果，以及映射后的文件在本机是否存在
+/// rules 为空时使用偏好设置中保存的规则
+#[tauri::command]
+pub fn preview_path_remap<R: Runtime>(
+    app: AppHandle<R>,
+    backup_path: String,
+    rules: Option<Vec<PathRemapRule>>,
+    project_root: Option<String>,
+) -> Result<Vec<RemapPreview>, String> {
+    let content = read_backup_content(Path::new(&backup_path))?;
+    let rules = match rules {
+        Some(rules) => rules,
+        None => load_prefs(&app)?.path_remap_rules,
+    };
+
+    let previews = collect_file_urls(&content)
+        .into_iter()
+        .map(|url| {
+            let remapped_url = if url.contains("$PROJECT_DIR$") {
+                url.clone()
+            } else {
+                remap_path(&clean_bookmark_path(&url, None), &rules)
+                    .map(|p| bookmark_url(&p, None))
+                    .unwrap_or_else(|| url.clone())
+            };
+
+            let resolved_path = if remapped_url.contains("$PROJECT_DIR$") {
+                project_root
+                    .as_deref()
+                    .map(|root| clean_bookmark_path(&remapped_url, Some(&normalize_path(root))))
+            } else {
+                Some(clean_bookmark_path(&remapped_url, None))
+            };
+            let resolves = resolved_path.as_deref().is_some_and(|p| Path::new(p).exists());
+
+            RemapPreview {
+                changed: remapped_url != url,
+                original_url: url,
+                remapped_url,
+                resolved_path,
+                resolves,
+            }
+        })
+        .collect();
+    Ok(previews)
+}
+
+/// 应用映射规则后恢复备份到目标文件，返回被改写的路径数量
+/// rules 为空时使用偏好设置中保存的规则
+#[tauri::command]
+pub fn restore_bookmark_file_remapped<R: Runtime>(
+    app: AppHandle<R>,
+    backup_path: String,
+    target_path: String,
+    rules: Option<Vec<PathRemapRule>>,
+) -> Result<usize, String> {
+    let content = read_backup_content(Path::new(&backup_path))?;
+    let rules = match rules {
+        Some(rules) => rules,
+        None => load_prefs(&app)?.path_remap_rules,
+    };
+    let (remapped, count) = apply_remap_rules(&content, &rules);
+
+    let target = Path::new(&target_path);
+    let op = journal::write_file(target, &remapped)?;
+    let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+    journal::record(&app, &format!("从备份恢复 {}（路径映射）", file_name), op);
+    Ok(count)
+}
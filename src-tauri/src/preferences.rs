@@ -1,49 +1,627 @@
-use std::fs;
-use std::path::PathBuf;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use chrono::Local;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
 
-#[derive(Serialize, Deserialize, Default)]
-struct UserPreferences {
+use crate::path_remap::PathRemapRule;
+
+/// 当前设置文件的结构版本；版本 1 为没有 schema_version 字段的扁平结构，版本 2 没有档案
+pub(crate) const SETTINGS_SCHEMA_VERSION: u32 = 3;
+
+/// 默认档案名，同时是默认的数据库命名空间
+pub(crate) const DEFAULT_PROFILE: &str = "default";
+
+/// 回收区默认保留天数
+pub(crate) const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+
+const SETTINGS_FILE: &str = "preferences.json";
+const MAX_TRASH_RETENTION_DAYS: u32 = 3650;
+const UI_THEMES: &[&str] = &["system", "light", "dark"];
+
+/// 通用命令中指向当前档案的键前缀，如 profile.backup.trash_retention_days
+const ACTIVE_PROFILE_PREFIX: &str = "profile.";
+
+/// 不能通过通用命令修改的设置项及原因
+const READ_ONLY_KEYS: &[(&str, &str)] = &[
+    ("schema_version", "由应用维护"),
+    ("machine_id", "由应用维护"),
+    ("profiles", "请使用档案相关命令管理档案"),
+    ("active_profile", "请使用 switch_profile 切换档案"),
+    ("profile.name", "档案名称创建后不能修改"),
+    ("profile.db_namespace", "数据库命名空间创建后不能修改"),
+    ("profile.backup.dir", "请使用 set_backup_location 更改备份目录，以便迁移已有备份"),
+    ("profile.backup.encrypt", "请使用 enable_backup_encryption / disable_backup_encryption"),
+    ("profile.backup.encryption_verifier", "由应用维护"),
+];
+
+/// 读取、校验或写入设置时的错误
+#[derive(Debug, thiserror::Error)]
+pub enum SettingsError {
+    #[error("无法定位应用数据目录：{0}")]
+    DataDir(String),
+    #[error("读写设置文件失败：{0}")]
+    Io(#[from] std::io::Error),
+    #[error("设置文件格式错误：{0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("设置文件的版本 {0} 高于当前支持的版本，请升级应用")]
+    FutureVersion(u32),
+    #[error("设置文件已损坏，请先重置设置（原文件会被保留）：{0}")]
+    Corrupt(String),
+    #[error("未知的设置项：{0}")]
+    UnknownKey(String),
+    #[error("设置项 {key} 无效：{reason}")]
+    Invalid { key: String, reason: String },
+}
+
+impl SettingsError {
+    fn invalid(key: &str, reason: impl Into<String>) -> Self {
+        SettingsError::Invalid { key: key.to_string(), reason: reason.into() }
+    }
+}
+
+/// 备份策略
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BackupSettings {
+    /// 自定义备份目录（如同步盘中的文件夹），未设置时使用应用数据目录下的 backups
+    pub dir: Option<String>,
+    /// 回收区中备份的保留天数，0 表示不自动清理
+    pub trash_retention_days: u32,
+    /// 新备份是否使用口令加密
+    pub encrypt: bool,
+    /// 用当前口令加密的已知内容（十六进制），用于校验口令
+    pub encryption_verifier: Option<String>,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        BackupSettings {
+            dir: None,
+            trash_retention_days: DEFAULT_TRASH_RETENTION_DAYS,
+            encrypt: false,
+            encryption_verifier: None,
+        }
+    }
+}
+
+/// IDE 配置目录的扫描选项
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ScanSettings {
+    /// JetBrains 配置根目录，未设置时使用系统默认位置
+    pub jetbrains_dir: Option<String>,
+    /// 识别为 IDE 版本目录的名称前缀，如 IntelliJIdea、IdeaIC
+    pub product_prefixes: Vec<String>,
+}
+
+impl Default for ScanSettings {
+    fn default() -> Self {
+        ScanSettings { jetbrains_dir: None, product_prefixes: vec!["IntelliJIdea".to_string()] }
+    }
+}
+
+/// IDE 启动设置
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct IdeSettings {
+    /// 自定义 IDE 启动命令模板，支持 {file} 与 {line} 占位符
+    pub command_template: Option<String>,
+}
+
+/// 界面默认值，供前端使用
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct UiSettings {
+    /// system / light / dark
+    pub theme: String,
+    /// 列表每页条数
+    pub page_size: u32,
+    /// 删除前是否确认
+    pub confirm_delete: bool,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        UiSettings { theme: "system".to_string(), page_size: 50, confirm_delete: true }
+    }
+}
+
+/// 档案：一套独立的 IDE 版本选择、备份策略与数据库
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    /// 上次选择的 IDEA 版本路径
+    pub last_idea_version: Option<String>,
+    pub backup: BackupSettings,
+    /// 数据库命名空间，决定使用的数据库文件与默认备份目录
+    pub db_namespace: String,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            name: DEFAULT_PROFILE.to_string(),
+            last_idea_version: None,
+            backup: BackupSettings::default(),
+            db_namespace: DEFAULT_PROFILE.to_string(),
+        }
+    }
+}
+
+impl Profile {
+    /// 按命名空间区分的数据文件或目录名，默认命名空间沿用原有名称（如 bookmarks、backups）
+    pub(crate) fn namespaced(&self, base: &str) -> String {
+        if self.db_namespace == DEFAULT_PROFILE {
+            base.to_string()
+        } else {
+            format!("{}-{}", base, self.db_namespace)
+        }
+    }
+}
+
+/// 数据库命名空间只允许字母、数字、- 与 _，用于拼接文件名
+pub(crate) fn is_valid_namespace(namespace: &str) -> bool {
+    !namespace.is_empty()
+        && namespace.len() <= 64
+        && namespace.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// 用户设置，保存在应用数据目录的 preferences.json 中
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Settings {
+    pub schema_version: u32,
+    /// 当前档案名
+    pub active_profile: String,
+    pub profiles: Vec<Profile>,
+    pub scan: ScanSettings,
+    /// 恢复备份时使用的路径前缀映射规则
+    pub path_remap_rules: Vec<PathRemapRule>,
+    pub ide: IdeSettings,
+    pub ui: UiSettings,
+    /// 本机标识，用于区分同步目录中其它机器写入的备份
+    pub machine_id: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            schema_version: SETTINGS_SCHEMA_VERSION,
+            active_profile: DEFAULT_PROFILE.to_string(),
+            profiles: vec![Profile::default()],
+            scan: ScanSettings::default(),
+            path_remap_rules: Vec::new(),
+            ide: IdeSettings::default(),
+            ui: UiSettings::default(),
+            machine_id: None,
+        }
+    }
+}
+
+impl Settings {
+    fn active_index(&self) -> usize {
+        self.profiles.iter().position(|p| p.name == self.active_profile).unwrap_or(0)
+    }
+
+    /// 当前档案
+    pub(crate) fn active(&self) -> &Profile {
+        &self.profiles[self.active_index()]
+    }
+
+    pub(crate) fn active_mut(&mut self) -> &mut Profile {
+        let index = self.active_index();
+        &mut self.profiles[index]
+    }
+
+    pub(crate) fn find_profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    /// 保证至少有一个档案且当前档案存在，用于读取手工编辑过的设置文件
+    fn normalize(&mut self) {
+        if self.profiles.is_empty() {
+            self.profiles.push(Profile::default());
+        }
+        if self.find_profile(&self.active_profile).is_none() {
+            self.active_profile = self.profiles[0].name.clone();
+        }
+    }
+
+    /// 检查各设置项的取值，返回第一个无效项
+    pub(crate) fn validate(&self) -> Result<(), SettingsError> {
+        if self.find_profile(&self.active_profile).is_none() {
+            return Err(SettingsError::invalid("active_profile", "档案不存在"));
+        }
+        for (i, profile) in self.profiles.iter().enumerate() {
+            let key = |field: &str| format!("profiles.{}.{}", profile.name, field);
+            if profile.name.trim().is_empty() {
+                return Err(SettingsError::invalid(&key("name"), "档案名称不能为空"));
+            }
+            if self.profiles[..i].iter().any(|p| p.name == profile.name) {
+                return Err(SettingsError::invalid(&key("name"), "档案名称重复"));
+            }
+            if !is_valid_namespace(&profile.db_namespace) {
+                return Err(SettingsError::invalid(&key("db_namespace"), "只能包含字母、数字、- 与 _"));
+            }
+            if self.profiles[..i].iter().any(|p| p.db_namespace == profile.db_namespace) {
+                return Err(SettingsError::invalid(&key("db_namespace"), "已被其它档案使用"));
+            }
+            if profile.backup.dir.as_ref().is_some_and(|d| !Path::new(d).is_absolute()) {
+                return Err(SettingsError::invalid(&key("backup.dir"), "必须是绝对路径"));
+            }
+            if profile.backup.trash_retention_days > MAX_TRASH_RETENTION_DAYS {
+                return Err(SettingsError::invalid(
+                    &key("backup.trash_retention_days"),
+                    format!("不能超过 {} 天", MAX_TRASH_RETENTION_DAYS),
+                ));
+            }
+        }
+        if self.scan.jetbrains_dir.as_ref().is_some_and(|d| !Path::new(d).is_absolute()) {
+            return Err(SettingsError::invalid("scan.jetbrains_dir", "必须是绝对路径"));
+        }
+        if self.scan.product_prefixes.is_empty() || self.scan.product_prefixes.iter().any(|p| p.trim().is_empty()) {
+            return Err(SettingsError::invalid("scan.product_prefixes", "至少需要一个非空前缀"));
+        }
+        if self.path_remap_rules.iter().any(|r| r.from.trim().is_empty()) {
+            return Err(SettingsError::invalid("path_remap_rules", "规则的 from 不能为空"));
+        }
+        if self.ide.command_template.as_ref().is_some_and(|t| !t.contains("{file}")) {
+            return Err(SettingsError::invalid("ide.command_template", "必须包含 {file} 占位符"));
+        }
+        if !UI_THEMES.contains(&self.ui.theme.as_str()) {
+            return Err(SettingsError::invalid("ui.theme", format!("可选值为 {}", UI_THEMES.join(" / "))));
+        }
+        if !(10..=500).contains(&self.ui.page_size) {
+            return Err(SettingsError::invalid("ui.page_size", "必须在 10 到 500 之间"));
+        }
+        Ok(())
+    }
+}
+
+/// 版本 1 的扁平结构
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct LegacyPreferences {
     last_idea_version: Option<String>,
+    ide_command_template: Option<String>,
+    path_remap_rules: Vec<PathRemapRule>,
+    trash_retention_days: Option<u32>,
+    backup_dir: Option<String>,
+    machine_id: Option<String>,
+    encrypt_backups: bool,
+    encryption_verifier: Option<String>,
+}
+
+impl LegacyPreferences {
+    /// 转换为版本 2 的结构
+    fn into_v2(self) -> Value {
+        serde_json::json!({
+            "last_idea_version": self.last_idea_version,
+            "backup": BackupSettings {
+                dir: self.backup_dir,
+                trash_retention_days: self.trash_retention_days.unwrap_or(DEFAULT_TRASH_RETENTION_DAYS),
+                encrypt: self.encrypt_backups,
+                encryption_verifier: self.encryption_verifier,
+            },
+            "path_remap_rules": self.path_remap_rules,
+            // 旧版本没有校验模板，缺少 {file} 的模板无法通过新版校验，迁移时丢弃并回到自动检测
+            "ide": IdeSettings { command_template: self.ide_command_template.filter(|t| t.contains("{file}")) },
+            "machine_id": self.machine_id,
+        })
+    }
 }
 
-fn get_prefs_path<R: Runtime>(app: &AppHandle<R>) -> PathBuf {
-    let app_dir = app.path().app_data_dir().expect("failed to get app data dir");
-    if !app_dir.exists() {
-        fs::create_dir_all(&app_dir).expect("failed to create app data dir");
+/// 版本 2 到 3：IDE 版本选择与备份策略移入默认档案，沿用原有数据库
+fn move_into_default_profile(mut value: Value) -> Value {
+    if let Some(obj) = value.as_object_mut() {
+        let profile = serde_json::json!({
+            "name": DEFAULT_PROFILE,
+            "last_idea_version": obj.remove("last_idea_version").unwrap_or(Value::Null),
+            "backup": obj.remove("backup").unwrap_or_else(|| serde_json::json!({})),
+            "db_namespace": DEFAULT_PROFILE,
+        });
+        obj.insert("profiles".to_string(), Value::Array(vec![profile]));
+        obj.insert("active_profile".to_string(), Value::from(DEFAULT_PROFILE));
+    }
+    value
+}
+
+/// 将任意版本的设置迁移到当前版本，返回 (设置, 原版本)
+fn migrate_settings(value: Value) -> Result<(Settings, u32), SettingsError> {
+    let version = value.get("schema_version").and_then(Value::as_u64).unwrap_or(1) as u32;
+    if version > SETTINGS_SCHEMA_VERSION {
+        return Err(SettingsError::FutureVersion(version));
+    }
+
+    let mut value = value;
+    if version < 2 {
+        let legacy: LegacyPreferences = serde_json::from_value(value)?;
+        value = legacy.into_v2();
+    }
+    if version < 3 {
+        value = move_into_default_profile(value);
     }
-    app_dir.join("preferences.json")
+    // 之后的结构变化在这里按版本依次追加迁移步骤
+
+    let mut settings: Settings = serde_json::from_value(value)?;
+    settings.schema_version = SETTINGS_SCHEMA_VERSION;
+    settings.normalize();
+    Ok((settings, version))
+}
+
+fn settings_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, SettingsError> {
+    let app_dir = app.path().app_data_dir().map_err(|e| SettingsError::DataDir(e.to_string()))?;
+    fs::create_dir_all(&app_dir)?;
+    Ok(app_dir.join(SETTINGS_FILE))
+}
+
+/// 读取并迁移设置文件，不做任何写入
+fn parse_settings_file(path: &Path) -> Result<(Settings, u32), SettingsError> {
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str::<Value>(&content)
+        .map_err(SettingsError::from)
+        .and_then(migrate_settings)
 }
 
-fn load_prefs<R: Runtime>(app: &AppHandle<R>) -> UserPreferences {
-    let path = get_prefs_path(app);
+/// 先写入临时文件再替换，避免写到一半时留下损坏的设置文件
+/// 现有文件无法读取时拒绝写入，以免覆盖用户尚未处理的损坏文件
+fn write_settings_file(path: &Path, settings: &Settings) -> Result<(), SettingsError> {
     if path.exists() {
-        fs::read_to_string(&path)
-            .ok()
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_default()
-    } else {
-        UserPreferences::default()
+        match parse_settings_file(path) {
+            Ok(_) => {}
+            Err(e @ SettingsError::FutureVersion(_)) => return Err(e),
+            Err(e) => return Err(SettingsError::Corrupt(e.to_string())),
+        }
+    }
+    let json = serde_json::to_string_pretty(settings)?;
+    let tmp = path.with_file_name(format!("{}.tmp", SETTINGS_FILE));
+    let mut file = File::create(&tmp)?;
+    file.write_all(json.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// 读取设置；旧版本会自动迁移并保留原文件副本
+/// 无法解析的文件保持原样并返回错误，直到用户通过 reset_corrupt_settings 处理，期间不会被默认值覆盖
+pub(crate) fn load_settings<R: Runtime>(app: &AppHandle<R>) -> Result<Settings, SettingsError> {
+    let path = settings_path(app)?;
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+
+    let (settings, version) = parse_settings_file(&path)?;
+    if version < SETTINGS_SCHEMA_VERSION {
+        fs::copy(&path, path.with_file_name(format!("{}.v{}.bak", SETTINGS_FILE, version)))?;
+        write_settings_file(&path, &settings)?;
+        println!("Migrated preferences from schema version {} to {}", version, SETTINGS_SCHEMA_VERSION);
+    }
+    Ok(settings)
+}
+
+/// 校验后保存设置
+pub(crate) fn save_settings<R: Runtime>(app: &AppHandle<R>, settings: &Settings) -> Result<(), SettingsError> {
+    settings.validate()?;
+    write_settings_file(&settings_path(app)?, settings)
+}
+
+/// 读取设置，供其它模块使用
+pub(crate) fn load_prefs<R: Runtime>(app: &AppHandle<R>) -> Result<Settings, String> {
+    load_settings(app).map_err(|e| e.to_string())
+}
+
+pub(crate) fn save_prefs<R: Runtime>(app: &AppHandle<R>, prefs: &Settings) -> Result<(), String> {
+    save_settings(app, prefs).map_err(|e| e.to_string())
+}
+
+/// 按点分隔的键（如 ui.page_size）查找设置项，profile. 开头的键指向当前档案
+fn lookup_mut<'a>(tree: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    let (root, path) = match key.strip_prefix(ACTIVE_PROFILE_PREFIX) {
+        Some(rest) => {
+            let active = tree.get("active_profile")?.as_str()?.to_string();
+            let profiles = tree.get_mut("profiles")?.as_array_mut()?;
+            let index = profiles
+                .iter()
+                .position(|p| p.get("name").and_then(Value::as_str) == Some(active.as_str()))
+                .unwrap_or(0);
+            (profiles.get_mut(index)?, rest)
+        }
+        None => (tree, key),
+    };
+    path.split('.').try_fold(root, |node, part| node.get_mut(part))
+}
+
+/// 该键本身、其上级或其下级是否为只读设置项
+fn check_writable(key: &str) -> Result<(), SettingsError> {
+    let prefix = format!("{}.", key);
+    let read_only = READ_ONLY_KEYS
+        .iter()
+        .find(|(k, _)| *k == key || k.starts_with(&prefix) || key.starts_with(&format!("{}.", k)));
+    match read_only {
+        Some((k, reason)) => Err(SettingsError::invalid(k, *reason)),
+        None => Ok(()),
+    }
+}
+
+/// 用 value 替换 tree 中 key 对应的值并转换回设置
+fn replace_value(tree: &mut Value, key: &str, value: Value) -> Result<Settings, SettingsError> {
+    let slot = lookup_mut(tree, key).ok_or_else(|| SettingsError::UnknownKey(key.to_string()))?;
+    *slot = value;
+    serde_json::from_value(tree.clone()).map_err(|e| SettingsError::invalid(key, e.to_string()))
+}
+
+/// 把无法读取的设置文件改名保留，之后使用默认设置；返回保留文件的路径
+/// 设置文件可以正常读取时不做任何改动
+pub(crate) fn set_aside_settings<R: Runtime>(app: &AppHandle<R>) -> Result<Option<PathBuf>, SettingsError> {
+    let path = settings_path(app)?;
+    match parse_settings_file(&path) {
+        Ok(_) => return Ok(None),
+        Err(SettingsError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(_) => {}
     }
+    let aside = path.with_file_name(format!("{}.corrupt-{}", SETTINGS_FILE, Local::now().format("%Y%m%d_%H%M%S")));
+    fs::rename(&path, &aside)?;
+    println!("Unreadable preferences moved to {:?}", aside);
+    Ok(Some(aside))
+}
+
+/// 启动时设置文件无法读取：提示用户重置设置或退出，而不是直接使用默认值
+/// 用户选择重置后再执行 on_reset 完成启动
+pub(crate) fn prompt_settings_recovery<R: Runtime>(
+    app: &AppHandle<R>,
+    error: SettingsError,
+    on_reset: impl FnOnce(&AppHandle<R>) + Send + 'static,
+) {
+    let message = format!(
+        "无法读取设置：{}\n\n重置后将使用默认设置，原设置文件会改名保留在应用数据目录中。",
+        error
+    );
+    let handle = app.clone();
+    app.dialog()
+        .message(message)
+        .title("设置文件损坏")
+        .kind(MessageDialogKind::Error)
+        .buttons(MessageDialogButtons::OkCancelCustom("重置设置".to_string(), "退出".to_string()))
+        .show(move |reset| {
+            if !reset {
+                handle.exit(1);
+                return;
+            }
+            match set_aside_settings(&handle) {
+                Ok(_) => on_reset(&handle),
+                Err(e) => {
+                    println!("Failed to reset preferences: {}", e);
+                    handle.exit(1);
+                }
+            }
+        });
+}
+
+/// 重置无法读取的设置文件（原文件改名保留），返回保留文件的路径；设置文件正常时返回 None
+#[tauri::command]
+pub fn reset_corrupt_settings<R: Runtime>(app: AppHandle<R>) -> Result<Option<String>, String> {
+    set_aside_settings(&app)
+        .map(|aside| aside.map(|p| p.to_string_lossy().to_string()))
+        .map_err(|e| e.to_string())
+}
+
+/// 获取全部设置
+#[tauri::command]
+pub fn get_settings<R: Runtime>(app: AppHandle<R>) -> Result<Settings, String> {
+    load_settings(&app).map_err(|e| e.to_string())
+}
+
+/// 获取单个设置项，key 为点分隔路径，如 ui.page_size；当前档案的设置项以 profile. 开头
+#[tauri::command]
+pub fn get_setting<R: Runtime>(app: AppHandle<R>, key: String) -> Result<Value, String> {
+    let settings = load_settings(&app).map_err(|e| e.to_string())?;
+    let mut tree = serde_json::to_value(settings).map_err(|e| e.to_string())?;
+    lookup_mut(&mut tree, &key)
+        .map(|v| v.take())
+        .ok_or_else(|| SettingsError::UnknownKey(key).to_string())
+}
+
+/// 修改单个设置项，校验通过后保存并返回全部设置
+#[tauri::command]
+pub fn set_setting<R: Runtime>(app: AppHandle<R>, key: String, value: Value) -> Result<Settings, String> {
+    let result = (|| -> Result<Settings, SettingsError> {
+        check_writable(&key)?;
+        let mut tree = serde_json::to_value(load_settings(&app)?)?;
+        let settings = replace_value(&mut tree, &key, value)?;
+        save_settings(&app, &settings)?;
+        Ok(settings)
+    })();
+    result.map_err(|e| e.to_string())
 }
 
-fn save_prefs<R: Runtime>(app: &AppHandle<R>, prefs: &UserPreferences) -> Result<(), String> {
-    let path = get_prefs_path(app);
-    let json = serde_json::to_string_pretty(prefs).map_err(|e| e.to_string())?;
-    fs::write(path, json).map_err(|e| e.to_string())
+/// 恢复默认设置；key 为空时恢复全部通用设置项，档案与本机标识保持不变
+#[tauri::command]
+pub fn reset_settings<R: Runtime>(app: AppHandle<R>, key: Option<String>) -> Result<Settings, String> {
+    let result = (|| -> Result<Settings, SettingsError> {
+        let current = load_settings(&app)?;
+        let mut defaults = serde_json::to_value(Settings::default())?;
+        let settings = match key {
+            Some(key) => {
+                check_writable(&key)?;
+                let default_value = lookup_mut(&mut defaults, &key)
+                    .map(|v| v.take())
+                    .ok_or_else(|| SettingsError::UnknownKey(key.clone()))?;
+                replace_value(&mut serde_json::to_value(current)?, &key, default_value)?
+            }
+            None => {
+                let mut tree = serde_json::to_value(current)?;
+                // 档案整体保留，不需要逐项处理当前档案的只读项
+                for (k, _) in READ_ONLY_KEYS.iter().filter(|(k, _)| !k.starts_with(ACTIVE_PROFILE_PREFIX)) {
+                    if let Some(kept) = lookup_mut(&mut tree, k).map(|v| v.take()) {
+                        replace_value(&mut defaults, k, kept)?;
+                    }
+                }
+                serde_json::from_value(defaults)?
+            }
+        };
+        save_settings(&app, &settings)?;
+        Ok(settings)
+    })();
+    result.map_err(|e| e.to_string())
 }
 
 /// 获取上次保存的 IDEA 版本路径
 #[tauri::command]
-pub fn get_saved_idea_version<R: Runtime>(app: AppHandle<R>) -> Option<String> {
-    load_prefs(&app).last_idea_version
+pub fn get_saved_idea_version<R: Runtime>(app: AppHandle<R>) -> Result<Option<String>, String> {
+    Ok(load_prefs(&app)?.active().last_idea_version.clone())
 }
 
 /// 保存用户选择的 IDEA 版本路径
 #[tauri::command]
 pub fn save_idea_version<R: Runtime>(app: AppHandle<R>, workspace_path: String) -> Result<(), String> {
-    let mut prefs = load_prefs(&app);
-    prefs.last_idea_version = Some(workspace_path);
+    let mut prefs = load_prefs(&app)?;
+    prefs.active_mut().last_idea_version = Some(workspace_path);
+    save_prefs(&app, &prefs)
+}
+
+/// 获取自定义的 IDE 启动命令模板
+#[tauri::command]
+pub fn get_ide_command_template<R: Runtime>(app: AppHandle<R>) -> Result<Option<String>, String> {
+    Ok(load_prefs(&app)?.ide.command_template)
+}
+
+/// 保存自定义的 IDE 启动命令模板，传空则恢复自动检测
+#[tauri::command]
+pub fn save_ide_command_template<R: Runtime>(app: AppHandle<R>, template: Option<String>) -> Result<(), String> {
+    let mut prefs = load_prefs(&app)?;
+    prefs.ide.command_template = template.filter(|t| !t.trim().is_empty());
+    save_prefs(&app, &prefs)
+}
+
+/// 获取保存的路径映射规则
+#[tauri::command]
+pub fn get_path_remap_rules<R: Runtime>(app: AppHandle<R>) -> Result<Vec<PathRemapRule>, String> {
+    Ok(load_prefs(&app)?.path_remap_rules)
+}
+
+/// 保存路径映射规则
+#[tauri::command]
+pub fn save_path_remap_rules<R: Runtime>(app: AppHandle<R>, rules: Vec<PathRemapRule>) -> Result<(), String> {
+    let mut prefs = load_prefs(&app)?;
+    prefs.path_remap_rules = rules.into_iter().filter(|r| !r.from.trim().is_empty()).collect();
+    save_prefs(&app, &prefs)
+}
+
+/// 获取回收区保留天数
+#[tauri::command]
+pub fn get_trash_retention_days<R: Runtime>(app: AppHandle<R>) -> Result<u32, String> {
+    Ok(load_prefs(&app)?.active().backup.trash_retention_days)
+}
+
+/// 保存回收区保留天数，0 表示不自动清理
+#[tauri::command]
+pub fn save_trash_retention_days<R: Runtime>(app: AppHandle<R>, days: u32) -> Result<(), String> {
+    let mut prefs = load_prefs(&app)?;
+    prefs.active_mut().backup.trash_retention_days = days;
     save_prefs(&app, &prefs)
 }
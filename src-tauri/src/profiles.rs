@@ -0,0 +1,194 @@
+use std::fs;
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::backup_crypto::lock_backups;
+use crate::db::{init_db, profile_db_path};
+use crate::preferences::{is_valid_namespace, load_settings, save_settings, Profile, Settings};
+
+/// 档案摘要
+#[derive(serde::Serialize, Debug)]
+pub struct ProfileSummary {
+    pub name: String,
+    pub db_namespace: String,
+    pub last_idea_version: Option<String>,
+    pub backup_dir: Option<String>,
+    pub encrypt_backups: bool,
+    pub active: bool,
+}
+
+fn summarize(settings: &Settings) -> Vec<ProfileSummary> {
+    settings
+        .profiles
+        .iter()
+        .map(|p| ProfileSummary {
+            name: p.name.clone(),
+            db_namespace: p.db_namespace.clone(),
+            last_idea_version: p.last_idea_version.clone(),
+            backup_dir: p.backup.dir.clone(),
+            encrypt_backups: p.backup.encrypt,
+            active: p.name == settings.active_profile,
+        })
+        .collect()
+}
+
+fn load<R: Runtime>(app: &AppHandle<R>) -> Result<Settings, String> {
+    load_settings(app).map_err(|e| e.to_string())
+}
+
+fn save<R: Runtime>(app: &AppHandle<R>, settings: &Settings) -> Result<(), String> {
+    save_settings(app, settings).map_err(|e| e.to_string())
+}
+
+/// 由档案名生成命名空间：非字母数字替换为 -，与已有命名空间冲突时追加序号
+fn derive_namespace(settings: &Settings, name: &str) -> String {
+    let base: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c.to_ascii_lowercase() } else { '-' })
+        .take(48)
+        .collect();
+    let base = match base.trim_matches('-') {
+        "" => "profile",
+        trimmed => trimmed,
+    };
+
+    let mut candidate = base.to_string();
+    let mut n = 2;
+    while settings.profiles.iter().any(|p| p.db_namespace == candidate) {
+        candidate = format!("{}-{}", base, n);
+        n += 1;
+    }
+    candidate
+}
+
+/// 校验名称与命名空间后加入档案列表
+fn add_profile(settings: &mut Settings, mut profile: Profile, db_namespace: Option<String>) -> Result<(), String> {
+    profile.name = profile.name.trim().to_string();
+    if profile.name.is_empty() {
+        return Err("档案名称不能为空".to_string());
+    }
+    if settings.find_profile(&profile.name).is_some() {
+        return Err(format!("档案已存在：{}", profile.name));
+    }
+
+    profile.db_namespace = match db_namespace.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()) {
+        Some(namespace) => {
+            if !is_valid_namespace(&namespace) {
+                return Err("数据库命名空间只能包含字母、数字、- 与 _".to_string());
+            }
+            if settings.profiles.iter().any(|p| p.db_namespace == namespace) {
+                return Err(format!("数据库命名空间已被其它档案使用：{}", namespace));
+            }
+            namespace
+        }
+        None => derive_namespace(settings, &profile.name),
+    };
+    settings.profiles.push(profile);
+    Ok(())
+}
+
+/// 列出所有档案
+#[tauri::command]
+pub fn list_profiles<R: Runtime>(app: AppHandle<R>) -> Result<Vec<ProfileSummary>, String> {
+    Ok(summarize(&load(&app)?))
+}
+
+/// 新建档案，使用默认备份策略；未指定命名空间时由名称生成
+#[tauri::command]
+pub fn create_profile<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    db_namespace: Option<String>,
+) -> Result<Vec<ProfileSummary>, String> {
+    let mut settings = load(&app)?;
+    add_profile(&mut settings, Profile { name, ..Profile::default() }, db_namespace)?;
+    save(&app, &settings)?;
+    Ok(summarize(&settings))
+}
+
+/// 复制档案的 IDE 版本选择与备份策略，使用新的数据库命名空间
+/// copy_data 为 true 时同时复制数据库（书签、备份记录、操作历史等）
+#[tauri::command]
+pub fn clone_profile<R: Runtime>(
+    app: AppHandle<R>,
+    source: String,
+    name: String,
+    db_namespace: Option<String>,
+    copy_data: bool,
+) -> Result<Vec<ProfileSummary>, String> {
+    let mut settings = load(&app)?;
+    let original = settings.find_profile(&source).cloned().ok_or(format!("档案不存在：{}", source))?;
+    add_profile(&mut settings, Profile { name, ..original.clone() }, db_namespace)?;
+    let cloned = settings.profiles.last().cloned().ok_or("Failed to clone profile")?;
+
+    if copy_data {
+        let from = profile_db_path(&app, &original);
+        let to = profile_db_path(&app, &cloned);
+        if to.exists() {
+            return Err(format!("目标数据库已存在：{}", to.display()));
+        }
+        if from.exists() {
+            fs::copy(&from, &to).map_err(|e| e.to_string())?;
+        }
+    }
+    if let Err(e) = save(&app, &settings) {
+        if copy_data {
+            let _ = fs::remove_file(profile_db_path(&app, &cloned));
+        }
+        return Err(e);
+    }
+    Ok(summarize(&settings))
+}
+
+/// 切换当前档案：初始化其数据库，并锁定加密备份（不同档案可能使用不同口令）
+#[tauri::command]
+pub fn switch_profile<R: Runtime>(app: AppHandle<R>, name: String) -> Result<Vec<ProfileSummary>, String> {
+    let mut settings = load(&app)?;
+    if settings.find_profile(&name).is_none() {
+        return Err(format!("档案不存在：{}", name));
+    }
+    if settings.active_profile != name {
+        settings.active_profile = name.clone();
+        save(&app, &settings)?;
+        lock_backups()?;
+    }
+    init_db(&app).map_err(|e| e.to_string())?;
+
+    println!("Switched to profile {}", name);
+    Ok(summarize(&settings))
+}
+
+/// 删除档案；不能删除当前档案
+/// delete_data 为 true 时同时删除其数据库与默认位置的备份，自定义备份目录不会被删除
+#[tauri::command]
+pub fn delete_profile<R: Runtime>(app: AppHandle<R>, name: String, delete_data: bool) -> Result<Vec<ProfileSummary>, String> {
+    let mut settings = load(&app)?;
+    if settings.active_profile == name {
+        return Err("不能删除当前档案，请先切换到其它档案".to_string());
+    }
+    let index = settings
+        .profiles
+        .iter()
+        .position(|p| p.name == name)
+        .ok_or(format!("档案不存在：{}", name))?;
+    let profile = settings.profiles.remove(index);
+    save(&app, &settings)?;
+
+    if delete_data {
+        let db_path = profile_db_path(&app, &profile);
+        if db_path.exists() {
+            fs::remove_file(&db_path).map_err(|e| e.to_string())?;
+        }
+        // 回收区始终位于应用数据目录；备份目录只在使用默认位置时删除
+        let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+        let mut dirs = vec![app_dir.join(profile.namespaced("backup_trash"))];
+        if profile.backup.dir.is_none() {
+            dirs.push(app_dir.join(profile.namespaced("backups")));
+        }
+        for dir in dirs.into_iter().filter(|d| d.exists()) {
+            fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+        }
+    }
+
+    println!("Deleted profile {} (data removed: {})", name, delete_data);
+    Ok(summarize(&settings))
+}
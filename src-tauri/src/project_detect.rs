@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// 项目级标记：IDEA 项目目录或仓库根目录，优先于构建文件
+const ROOT_MARKERS: &[&str] = &[".idea", ".git"];
+
+/// 构建文件标记：没有项目级标记时取离文件最近的一个
+const BUILD_MARKERS: &[&str] = &["Cargo.toml", "pom.xml", "build.gradle", "build.gradle.kts", "package.json"];
+
+/// 目录 -> 项目根目录的缓存，避免同一目录下的书签重复访问文件系统
+static ROOT_CACHE: OnceLock<Mutex<HashMap<PathBuf, Option<PathBuf>>>> = OnceLock::new();
+
+fn has_marker(dir: &Path, markers: &[&str]) -> bool {
+    markers.iter().any(|m| dir.join(m).exists())
+}
+
+/// boundary 限定向上查找的最高目录（包含该目录），None 表示查找到文件系统根目录
+fn detect_uncached(dir: &Path, boundary: Option<&Path>) -> Option<PathBuf> {
+    let ancestors: Vec<&Path> = dir
+        .ancestors()
+        .filter(|p| p.file_name().is_some())
+        .take_while(|p| boundary.is_none_or(|b| p.starts_with(b)))
+        .collect();
+    ancestors
+        .iter()
+        .find(|d| has_marker(d, ROOT_MARKERS))
+        .or_else(|| ancestors.iter().find(|d| has_marker(d, BUILD_MARKERS)))
+        .map(|d| d.to_path_buf())
+}
+
+/// 从文件所在目录向上查找项目根目录
+/// 最近的 `.idea` / `.git` 目录优先；都没有时取最近的构建文件所在目录
+/// monorepo 中子模块的构建文件不会截断查找：IDEA 按打开的项目（通常是仓库根目录）记录书签，
+/// 子 crate、子模块的书签应归入同一个项目
+pub(crate) fn find_project_root(file_path: &str) -> Option<PathBuf> {
+    let path = Path::new(file_path);
+    let dir = if path.is_dir() { path } else { path.parent()? };
+
+    let cache = ROOT_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Ok(cache) = cache.lock() {
+        if let Some(root) = cache.get(dir) {
+            return root.clone();
+        }
+    }
+
+    let root = detect_uncached(dir, None);
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(dir.to_path_buf(), root.clone());
+    }
+    root
+}
+
+/// 推断文件所属的项目名：项目根目录的目录名，找不到标记时退回到文件所在目录名
+pub(crate) fn detect_project_name(file_path: &str) -> String {
+    project_name_for(file_path, find_project_root(file_path).as_deref())
+}
+
+fn project_name_for(file_path: &str, root: Option<&Path>) -> String {
+    root.or_else(|| Path::new(file_path).parent())
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or("Unknown".to_string())
+}
+
+/// 清空项目根目录缓存（例如用户新建了 .git 或移动了项目之后）
+#[tauri::command]
+pub fn clear_project_cache() {
+    if let Some(cache) = ROOT_CACHE.get() {
+        if let Ok(mut cache) = cache.lock() {
+            cache.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// 测试用的临时目录树，离开作用域时删除
+    /// 临时目录本身可能位于带 .git / .idea 的目录中，查找时以树的根目录为边界
+    struct TempTree(PathBuf);
+
+    impl TempTree {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("project_detect_{}_{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            TempTree(root)
+        }
+
+        fn dir(&self, rel: &str) -> PathBuf {
+            let dir = self.0.join(rel);
+            fs::create_dir_all(&dir).unwrap();
+            dir
+        }
+
+        fn file(&self, rel: &str) -> String {
+            let path = self.0.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "").unwrap();
+            path.to_string_lossy().to_string()
+        }
+
+        fn root_of(&self, file: &str) -> Option<PathBuf> {
+            detect_uncached(Path::new(file).parent().unwrap(), Some(&self.0))
+        }
+
+        fn name_of(&self, file: &str) -> String {
+            project_name_for(file, self.root_of(file).as_deref())
+        }
+    }
+
+    impl Drop for TempTree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn resources_dir_is_not_mistaken_for_src() {
+        let tree = TempTree::new("resources");
+        tree.dir("home/u/resources/app/.git");
+        let file = tree.file("home/u/resources/app/x.rs");
+        assert_eq!(tree.name_of(&file), "app");
+    }
+
+    #[test]
+    fn project_name_containing_src() {
+        let tree = TempTree::new("srcutils");
+        tree.dir("srcutils/.idea");
+        let file = tree.file("srcutils/src/main/java/Util.java");
+        assert_eq!(tree.name_of(&file), "srcutils");
+    }
+
+    #[test]
+    fn no_markers_falls_back_to_parent_dir() {
+        let tree = TempTree::new("no_markers");
+        let file = tree.file("loose/notes/todo.txt");
+        assert_eq!(tree.root_of(&file), None);
+        assert_eq!(tree.name_of(&file), "notes");
+    }
+
+    #[test]
+    fn repo_root_wins_over_nested_build_file() {
+        let tree = TempTree::new("monorepo");
+        tree.dir("mono/.git");
+        tree.file("mono/crates/core/Cargo.toml");
+        let file = tree.file("mono/crates/core/src/lib.rs");
+        assert_eq!(tree.root_of(&file), Some(tree.0.join("mono")));
+        assert_eq!(tree.name_of(&file), "mono");
+    }
+
+    #[test]
+    fn nearest_build_file_without_root_marker() {
+        let tree = TempTree::new("build_files");
+        tree.file("ws/package.json");
+        tree.file("ws/packages/web/package.json");
+        let file = tree.file("ws/packages/web/index.js");
+        assert_eq!(tree.name_of(&file), "web");
+    }
+
+    #[test]
+    fn boundary_stops_the_search() {
+        let tree = TempTree::new("boundary");
+        tree.dir("outer/.git");
+        let file = tree.file("outer/inner/a.rs");
+        let inner = tree.0.join("outer/inner");
+        assert_eq!(detect_uncached(&inner, Some(&inner)), None);
+        assert_eq!(tree.root_of(&file), Some(tree.0.join("outer")));
+    }
+
+    #[test]
+    fn cached_until_cleared() {
+        let tree = TempTree::new("cache");
+        let file = tree.file("cached/module/main.rs");
+        // 临时目录之上可能已有标记，只比较缓存前后的结果
+        let first = find_project_root(&file);
+        assert_ne!(first, Some(tree.0.join("cached")));
+
+        // 新建标记后仍命中缓存，清空缓存后才重新查找，最近的 .git 优先
+        tree.dir("cached/.git");
+        assert_eq!(find_project_root(&file), first);
+        clear_project_cache();
+        assert_eq!(find_project_root(&file), Some(tree.0.join("cached")));
+    }
+}
@@ -0,0 +1,514 @@
+use std::fs;
+use std::path::Path;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::{AppHandle, Runtime};
+
+use crate::annotations::move_annotations;
+use crate::bookmark_manager::{attr_value_range, clean_bookmark_path, extract_attr_value};
+use crate::collections::{move_collection_items, set_collection_items_line};
+use crate::db::{load_bookmarks_by_ids, open_db};
+use crate::journal::{self, record_operation, Operation};
+
+/// 默认保存的上下文行数（书签行上下各取几行）
+const DEFAULT_CONTEXT_LINES: usize = 3;
+/// 默认最低置信度，低于该值视为无法定位
+const DEFAULT_MIN_CONFIDENCE: f64 = 0.6;
+/// 参与相似度计算的单行最大字符数，避免超长行拖慢匹配
+const MAX_COMPARE_CHARS: usize = 400;
+
+/// 书签锚点：书签行原文及其上下文
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct BookmarkAnchor {
+    pub file_path: String,
+    pub line_number: i32,
+    pub line_text: String,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+    pub captured_at: String,
+}
+
+/// 重新定位结果
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct RelocationResult {
+    pub file_path: String,
+    /// 原行号（1-indexed）
+    pub original_line: i32,
+    /// 匹配到的新行号，置信度不足时为空
+    pub new_line: Option<i32>,
+    /// 最佳匹配的置信度 (0.0 ~ 1.0)
+    pub confidence: f64,
+    /// 新行号处的代码内容
+    pub line_text: Option<String>,
+    /// 书签仍在原位置，无需改写
+    pub unchanged: bool,
+}
+
+fn read_lines(file_path: &str) -> Result<Vec<String>, String> {
+    let content = fs::read_to_string(file_path).map_err(|e| e.to_string())?;
+    Ok(content.lines().map(|l| l.to_string()).collect())
+}
+
+/// 规范化代码行：去掉首尾空白并合并连续空白，忽略缩进变化
+fn normalize_line(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 基于编辑距离的相似度 (0.0 ~ 1.0)
+fn line_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = normalize_line(a).chars().take(MAX_COMPARE_CHARS).collect();
+    let b: Vec<char> = normalize_line(b).chars().take(MAX_COMPARE_CHARS).collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        curr[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    let distance = prev[b.len()];
+    1.0 - distance as f64 / a.len().max(b.len()) as f64
+}
+
+/// 计算候选行（0-indexed）与锚点上下文的相似度
+fn context_similarity(lines: &[String], idx: usize, anchor: &BookmarkAnchor) -> f64 {
+    let total = anchor.context_before.len() + anchor.context_after.len();
+    if total == 0 {
+        return 1.0;
+    }
+
+    let mut sum = 0.0;
+    // 上文从靠近书签的一行开始逐行向上比较
+    for (k, expected) in anchor.context_before.iter().rev().enumerate() {
+        if let Some(actual) = idx.checked_sub(k + 1).and_then(|i| lines.get(i)) {
+            sum += line_similarity(actual, expected);
+        }
+    }
+    for (k, expected) in anchor.context_after.iter().enumerate() {
+        if let Some(actual) = lines.get(idx + k + 1) {
+            sum += line_similarity(actual, expected);
+        }
+    }
+    sum / total as f64
+}
+
+/// 在当前文件内容中寻找与锚点最匹配的行，返回 (0-indexed 行号, 置信度)
+fn find_best_match(lines: &[String], anchor: &BookmarkAnchor) -> Option<(usize, f64)> {
+    let original_idx = (anchor.line_number - 1).max(0) as usize;
+    let mut best: Option<(usize, f64)> = None;
+
+    for (idx, line) in lines.iter().enumerate() {
+        let score = 0.6 * line_similarity(line, &anchor.line_text)
+            + 0.4 * context_similarity(lines, idx, anchor);
+
+        let better = match best {
+            None => true,
+            Some((best_idx, best_score)) => {
+                // 分数相同时优先选择离原位置更近的行
+                score > best_score + f64::EPSILON
+                    || ((score - best_score).abs() <= f64::EPSILON
+                        && idx.abs_diff(original_idx) < best_idx.abs_diff(original_idx))
+            }
+        };
+        if better {
+            best = Some((idx, score));
+        }
+    }
+    best
+}
+
+fn load_anchor(conn: &Connection, file_path: &str, line_number: i32) -> Result<Option<BookmarkAnchor>, String> {
+    conn.query_row(
+        "SELECT file_path, line_number, line_text, context_before, context_after, captured_at
+         FROM bookmark_anchors WHERE file_path = ?1 AND line_number = ?2",
+        params![file_path, line_number],
+        |row| {
+            let before: String = row.get(3)?;
+            let after: String = row.get(4)?;
+            Ok(BookmarkAnchor {
+                file_path: row.get(0)?,
+                line_number: row.get(1)?,
+                line_text: row.get(2)?,
+                context_before: serde_json::from_str(&before).unwrap_or_default(),
+                context_after: serde_json::from_str(&after).unwrap_or_default(),
+                captured_at: row.get(5)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn save_anchor(conn: &Connection, anchor: &BookmarkAnchor) -> Result<(), String> {
+    let before = serde_json::to_string(&anchor.context_before).map_err(|e| e.to_string())?;
+    let after = serde_json::to_string(&anchor.context_after).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO bookmark_anchors (file_path, line_number, line_text, context_before, context_after, captured_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![anchor.file_path, anchor.line_number, anchor.line_text, before, after, anchor.captured_at],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 从文件当前内容构建锚点
+fn build_anchor(file_path: &str, line_number: i32, context_lines: usize) -> Result<BookmarkAnchor, String> {
+    let lines = read_lines(file_path)?;
+    if line_number < 1 || line_number as usize > lines.len() {
+        return Err(format!("Line {} is out of range ({} lines)", line_number, lines.len()));
+    }
+
+    let idx = (line_number - 1) as usize;
+    let before_start = idx.saturating_sub(context_lines);
+    let after_end = (idx + 1 + context_lines).min(lines.len());
+
+    Ok(BookmarkAnchor {
+        file_path: file_path.to_string(),
+        line_number,
+        line_text: lines[idx].clone(),
+        context_before: lines[before_start..idx].to_vec(),
+        context_after: lines[idx + 1..after_end].to_vec(),
+        captured_at: Utc::now().to_rfc3339(),
+    })
+}
+
+/// 将锚点从旧行号迁移到新行号，并以文件当前内容刷新上下文
+fn move_anchor(conn: &Connection, file_path: &str, old_line: i32, new_line: i32) -> Result<(), String> {
+    let context_lines = load_anchor(conn, file_path, old_line)?
+        .map(|a| a.context_before.len().max(a.context_after.len()))
+        .unwrap_or(DEFAULT_CONTEXT_LINES);
+
+    conn.execute(
+        "DELETE FROM bookmark_anchors WHERE file_path = ?1 AND line_number = ?2",
+        params![file_path, old_line],
+    )
+    .map_err(|e| e.to_string())?;
+
+    if Path::new(file_path).exists() {
+        let anchor = build_anchor(file_path, new_line, context_lines)?;
+        save_anchor(conn, &anchor)?;
+    }
+    Ok(())
+}
+
+/// 书签行号变化后，同步迁移其本地信息、锚点与合集步骤，返回被迁移的合集步骤 ID
+pub(crate) fn move_bookmark_data(
+    conn: &Connection,
+    project_name: &str,
+    file_path: &str,
+    old_line: i32,
+    new_line: i32,
+) -> Result<Vec<i64>, String> {
+    move_annotations(conn, project_name, file_path, old_line, new_line)?;
+    move_anchor(conn, file_path, old_line, new_line)?;
+    move_collection_items(conn, project_name, file_path, old_line, new_line)
+}
+
+/// 撤销行号迁移：本地信息与合集步骤移回原行，并恢复迁移前的锚点
+pub(crate) fn restore_bookmark_data(
+    conn: &Connection,
+    project_name: &str,
+    file_path: &str,
+    old_line: i32,
+    new_line: i32,
+    anchor: Option<&BookmarkAnchor>,
+    collection_items: &[i64],
+) -> Result<(), String> {
+    move_annotations(conn, project_name, file_path, new_line, old_line)?;
+    set_collection_items_line(conn, collection_items, old_line)?;
+    conn.execute(
+        "DELETE FROM bookmark_anchors WHERE file_path = ?1 AND line_number = ?2",
+        params![file_path, new_line],
+    )
+    .map_err(|e| e.to_string())?;
+    match anchor {
+        Some(anchor) => save_anchor(conn, anchor),
+        None => Ok(()),
+    }
+}
+
+/// 检查新行号在文件范围内；文件不存在时只检查行号为正
+fn check_target_line(file_path: &str, new_line: i32) -> Result<(), String> {
+    if new_line < 1 {
+        return Err(format!("Invalid line number: {}", new_line));
+    }
+    if Path::new(file_path).exists() {
+        let count = read_lines(file_path)?.len();
+        if new_line as usize > count {
+            return Err(format!("Line {} is out of range ({} lines)", new_line, count));
+        }
+    }
+    Ok(())
+}
+
+/// 在 XML 文本中定位指定书签并改写其行号 (同时支持 2025+ 新格式和旧格式)
+/// 行号参数均为 1-indexed，找不到对应书签时返回 None
+pub(crate) fn rewrite_bookmark_line(content: &str, bookmark_path: &str, old_line: i32, new_line: i32) -> Option<String> {
+    let old_value = (old_line - 1).to_string();
+    let new_value = (new_line - 1).to_string();
+
+    // 新格式: <BookmarkState> ... <entry key="url" .../> <entry key="line" .../> ... </BookmarkState>
+    let mut offset = 0;
+    while let Some(rel_start) = content[offset..].find("<BookmarkState>") {
+        let start = offset + rel_start;
+        let end = content[start..]
+            .find("</BookmarkState>")
+            .map(|e| start + e)
+            .unwrap_or(content.len());
+        let block = &content[start..end];
+        offset = end;
+
+        let url = match block.find("key=\"url\"") {
+            Some(idx) => extract_attr_value(&block[idx..], "value"),
+            None => None,
+        };
+        if url.map(|u| clean_bookmark_path(&u, None)).as_deref() != Some(bookmark_path) {
+            continue;
+        }
+
+        match block.find("key=\"line\"") {
+            Some(line_idx) => {
+                if let Some((s, e)) = attr_value_range(&block[line_idx..], "value") {
+                    let (s, e) = (start + line_idx + s, start + line_idx + e);
+                    if content[s..e] == old_value {
+                        return Some(format!("{}{}{}", &content[..s], new_value, &content[e..]));
+                    }
+                }
+            }
+            None if old_line == 1 => {
+                // 行号为 0 时 IDEA 可能省略 line 条目，紧跟 url 条目补上
+                let url_idx = block.find("key=\"url\"")?;
+                let close = start + url_idx + block[url_idx..].find("/>")? + 2;
+                let entry = format!("\n            <entry key=\"line\" value=\"{}\" />", new_value);
+                return Some(format!("{}{}{}", &content[..close], entry, &content[close..]));
+            }
+            None => {}
+        }
+    }
+
+    // 旧格式: <bookmark url="..." line="..." description="..." />
+    let mut offset = 0;
+    while let Some(rel_start) = content[offset..].find("<bookmark ") {
+        let start = offset + rel_start;
+        let end = content[start..].find('>').map(|e| start + e).unwrap_or(content.len());
+        let tag = &content[start..end];
+        offset = end;
+
+        let url = extract_attr_value(tag, "url");
+        if url.map(|u| clean_bookmark_path(&u, None)).as_deref() != Some(bookmark_path) {
+            continue;
+        }
+        if let Some((s, e)) = attr_value_range(tag, "line") {
+            let (s, e) = (start + s, start + e);
+            if content[s..e] == old_value {
+                return Some(format!("{}{}{}", &content[..s], new_value, &content[e..]));
+            }
+        }
+    }
+
+    None
+}
+
+/// 记录书签锚点（书签行原文及上下文），供之后重新定位使用
+#[tauri::command]
+pub fn capture_bookmark_anchor<R: Runtime>(
+    app: AppHandle<R>,
+    file_path: String,
+    line_number: i32,
+    context_lines: Option<usize>,
+) -> Result<BookmarkAnchor, String> {
+    let anchor = build_anchor(&file_path, line_number, context_lines.unwrap_or(DEFAULT_CONTEXT_LINES))?;
+    let conn = open_db(&app)?;
+    save_anchor(&conn, &anchor)?;
+    Ok(anchor)
+}
+
+/// 根据已保存的锚点，在当前文件中模糊匹配书签的新位置
+#[tauri::command]
+pub fn relocate_bookmark<R: Runtime>(
+    app: AppHandle<R>,
+    file_path: String,
+    line_number: i32,
+    min_confidence: Option<f64>,
+) -> Result<RelocationResult, String> {
+    let conn = open_db(&app)?;
+    let anchor = load_anchor(&conn, &file_path, line_number)?
+        .ok_or("No anchor recorded for this bookmark")?;
+    let lines = read_lines(&file_path)?;
+    let threshold = min_confidence.unwrap_or(DEFAULT_MIN_CONFIDENCE);
+
+    let (idx, confidence) = match find_best_match(&lines, &anchor) {
+        Some(m) => m,
+        None => {
+            return Ok(RelocationResult {
+                file_path,
+                original_line: line_number,
+                new_line: None,
+                confidence: 0.0,
+                line_text: None,
+                unchanged: false,
+            });
+        }
+    };
+
+    let new_line = (idx + 1) as i32;
+    let accepted = confidence >= threshold;
+    Ok(RelocationResult {
+        file_path,
+        original_line: line_number,
+        new_line: if accepted { Some(new_line) } else { None },
+        confidence,
+        line_text: if accepted { lines.get(idx).cloned() } else { None },
+        unchanged: accepted && new_line == line_number,
+    })
+}
+
+/// 将重新定位后的行号写回 IDEA workspace XML 文件
+#[tauri::command]
+pub fn apply_relocation_to_workspace<R: Runtime>(
+    app: AppHandle<R>,
+    xml_path: String,
+    project_name: String,
+    bookmark_path: String,
+    old_line: i32,
+    new_line: i32,
+) -> Result<(), String> {
+    check_target_line(&bookmark_path, new_line)?;
+    let content = fs::read_to_string(&xml_path).map_err(|e| e.to_string())?;
+    let updated = rewrite_bookmark_line(&content, &bookmark_path, old_line, new_line)
+        .ok_or("Bookmark not found in workspace file")?;
+
+    // 本地信息和锚点在同一事务中迁移，目标行已有本地信息时不改写 workspace 文件
+    let mut conn = open_db(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let anchor = load_anchor(&tx, &bookmark_path, old_line)?;
+    let collection_items = move_bookmark_data(&tx, &project_name, &bookmark_path, old_line, new_line)?;
+
+    let op = journal::write_file(Path::new(&xml_path), &updated)?;
+    let line_move = Operation::LineMove {
+        project_name,
+        file_path: bookmark_path,
+        old_line,
+        new_line,
+        anchor,
+        collection_items,
+    };
+    let result = record_operation(
+        &tx,
+        &format!("书签从第 {} 行移动到第 {} 行", old_line, new_line),
+        &Operation::Batch { operations: vec![op, line_move] },
+    )
+    .and_then(|_| tx.commit().map_err(|e| e.to_string()));
+    // 数据库写入失败时还原 workspace 文件，保持两者一致
+    if result.is_err() {
+        let _ = fs::write(&xml_path, &content);
+    }
+    result
+}
+
+/// 将重新定位后的行号写回本地数据库中的书签
+#[tauri::command]
+pub fn apply_relocation_to_db<R: Runtime>(app: AppHandle<R>, id: i64, new_line: i32) -> Result<(), String> {
+    let mut conn = open_db(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let before = load_bookmarks_by_ids(&tx, &[id])?;
+    let bookmark = before.first().cloned().ok_or(format!("Bookmark {} not found", id))?;
+    let old_line = bookmark.line_number;
+    check_target_line(&bookmark.file_path, new_line)?;
+    let anchor = load_anchor(&tx, &bookmark.file_path, old_line)?;
+
+    tx.execute(
+        "UPDATE bookmarks SET line_number = ?1 WHERE id = ?2",
+        params![new_line, id],
+    )
+    .map_err(|e| e.to_string())?;
+    let after = load_bookmarks_by_ids(&tx, &[id])?;
+    let collection_items = move_bookmark_data(&tx, &bookmark.project, &bookmark.file_path, old_line, new_line)?;
+
+    let line_move = Operation::LineMove {
+        project_name: bookmark.project,
+        file_path: bookmark.file_path,
+        old_line,
+        new_line,
+        anchor,
+        collection_items,
+    };
+    record_operation(
+        &tx,
+        &format!("书签从第 {} 行移动到第 {} 行", old_line, new_line),
+        &Operation::Batch { operations: vec![Operation::BookmarkRows { before, after }, line_move] },
+    )?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|l| l.to_string()).collect()
+    }
+
+    fn anchor(line_number: i32, line_text: &str, before: &[&str], after: &[&str]) -> BookmarkAnchor {
+        BookmarkAnchor {
+            file_path: "test.rs".to_string(),
+            line_number,
+            line_text: line_text.to_string(),
+            context_before: lines(before),
+            context_after: lines(after),
+            captured_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn similarity_ignores_whitespace_changes() {
+        assert_eq!(line_similarity("let x = 1;", "    let  x = 1;"), 1.0);
+        assert_eq!(line_similarity("", "   "), 1.0);
+    }
+
+    #[test]
+    fn similarity_decreases_with_edit_distance() {
+        let close = line_similarity("let count = 1;", "let count = 2;");
+        let far = line_similarity("let count = 1;", "fn main() {}");
+        assert!(close > 0.9 && close < 1.0);
+        assert!(far < 0.5);
+        assert_eq!(line_similarity("abc", ""), 0.0);
+    }
+
+    #[test]
+    fn finds_line_shifted_by_insertions() {
+        let anchor = anchor(2, "fn target() {", &["// header"], &["    body();"]);
+        let current = lines(&["// new", "// new", "// header", "fn target() {", "    body();", "}"]);
+        let (idx, confidence) = find_best_match(&current, &anchor).unwrap();
+        assert_eq!(idx, 3);
+        assert_eq!(confidence, 1.0);
+    }
+
+    #[test]
+    fn context_breaks_ties_between_identical_lines() {
+        let anchor = anchor(5, "}", &["    second();"], &[]);
+        let current = lines(&["fn a() {", "    first();", "}", "fn b() {", "    second();", "}"]);
+        assert_eq!(find_best_match(&current, &anchor).unwrap().0, 5);
+    }
+
+    #[test]
+    fn equal_scores_prefer_nearest_to_original_line() {
+        let anchor = anchor(4, "x();", &[], &[]);
+        let current = lines(&["x();", "y();", "z();", "x();", "w();"]);
+        assert_eq!(find_best_match(&current, &anchor).unwrap().0, 3);
+    }
+
+    #[test]
+    fn empty_file_has_no_match() {
+        assert!(find_best_match(&[], &anchor(1, "x", &[], &[])).is_none());
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use rusqlite::params;
+use tauri::{AppHandle, Runtime};
+
+use crate::bookmark_manager::read_bookmarks_from_workspace;
+use crate::db::open_db;
+use crate::project_detect::detect_project_name;
+
+/// 每个分类默认返回的条目数
+const DEFAULT_TOP: usize = 20;
+
+/// 书签年龄分段：(标签, 天数上限)
+const AGE_BUCKETS: &[(&str, i64)] = &[
+    ("今天", 1),
+    ("一周内", 7),
+    ("一个月内", 30),
+    ("三个月内", 90),
+    ("一年内", 365),
+];
+const AGE_OLDER: &str = "一年以上";
+const AGE_UNKNOWN: &str = "未知";
+
+/// 统计中的一个分类及其书签数量
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct StatBucket {
+    pub key: String,
+    pub count: usize,
+}
+
+/// 本地数据库中书签的创建时间统计；workspace 中的书签没有创建时间，只能单独统计
+#[derive(serde::Serialize, Debug)]
+pub struct LocalAgeStats {
+    /// 参与统计的本地书签数量，与 BookmarkStats.total 的来源不同，两者不一定相等
+    pub total: usize,
+    /// 按创建时间的年龄分布，顺序固定，从新到旧
+    pub age_distribution: Vec<StatBucket>,
+    pub oldest: Option<String>,
+    pub newest: Option<String>,
+}
+
+/// 书签统计，除 local_ages 外的数字都取自 workspace 中的书签
+#[derive(serde::Serialize, Debug)]
+pub struct BookmarkStats {
+    pub total: usize,
+    pub project_count: usize,
+    pub by_project: Vec<StatBucket>,
+    /// 按文件扩展名，没有扩展名的文件归入空字符串
+    pub by_extension: Vec<StatBucket>,
+    /// 按所在目录
+    pub by_directory: Vec<StatBucket>,
+    /// 按 IDEA 书签分组，没有分组名的旧格式书签不计入
+    pub by_group: Vec<StatBucket>,
+    pub by_mnemonic: Vec<StatBucket>,
+    pub with_mnemonic: usize,
+    pub with_description: usize,
+    /// 有描述的书签占比 (0.0 ~ 1.0)
+    pub description_coverage: f64,
+    /// 本地数据库中书签的年龄统计，独立于以上数字
+    pub local_ages: LocalAgeStats,
+}
+
+/// 计数后按数量降序、名称升序排列，只保留前 top 个
+fn top_buckets(counts: HashMap<String, usize>, top: usize) -> Vec<StatBucket> {
+    let mut buckets: Vec<StatBucket> = counts.into_iter().map(|(key, count)| StatBucket { key, count }).collect();
+    buckets.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    buckets.truncate(top);
+    buckets
+}
+
+fn age_bucket(created_at: &str, now: DateTime<Utc>) -> &'static str {
+    let Ok(created) = DateTime::parse_from_rfc3339(created_at) else {
+        return AGE_UNKNOWN;
+    };
+    let days = (now - created.with_timezone(&Utc)).num_days();
+    AGE_BUCKETS
+        .iter()
+        .find(|(_, limit)| days < *limit)
+        .map(|(label, _)| *label)
+        .unwrap_or(AGE_OLDER)
+}
+
+/// 计算书签的分类统计：总数、项目、扩展名、目录、分组、助记符与描述覆盖率都取自 workspace 中的书签，
+/// 与列表中展示的一致；本地数据库中书签的年龄分布单独放在 local_ages 中
+/// project 可限定单个项目；top 限制每个分类返回的条目数，默认 20
+#[tauri::command]
+pub fn get_bookmark_stats<R: Runtime>(
+    app: AppHandle<R>,
+    workspace_path: String,
+    project: Option<String>,
+    top: Option<usize>,
+) -> Result<BookmarkStats, String> {
+    let top = top.filter(|t| *t > 0).unwrap_or(DEFAULT_TOP);
+
+    let mut total = 0;
+    let mut with_description = 0;
+    let mut projects = HashMap::new();
+    let mut extensions = HashMap::new();
+    let mut directories = HashMap::new();
+    let mut groups = HashMap::new();
+    let mut mnemonics = HashMap::new();
+
+    for b in read_bookmarks_from_workspace(workspace_path)? {
+        // 旧格式书签没有分组名，按文件路径推断项目
+        let project_name = if b.project_name.is_empty() { detect_project_name(&b.file_path) } else { b.project_name.clone() };
+        if project.as_ref().is_some_and(|p| *p != project_name) {
+            continue;
+        }
+        total += 1;
+        if !b.description.trim().is_empty() {
+            with_description += 1;
+        }
+        *projects.entry(project_name).or_default() += 1;
+
+        let normalized = b.file_path.replace('\\', "/");
+        let path = Path::new(&normalized);
+        let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+        *extensions.entry(extension).or_default() += 1;
+        let directory = path.parent().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+        *directories.entry(directory).or_default() += 1;
+
+        if !b.project_name.is_empty() {
+            *groups.entry(b.project_name).or_default() += 1;
+        }
+        if let Some(mnemonic) = b.mnemonic.filter(|m| !m.is_empty()) {
+            *mnemonics.entry(mnemonic.to_uppercase()).or_default() += 1;
+        }
+    }
+
+    let conn = open_db(&app)?;
+    let mut stmt = conn
+        .prepare("SELECT created_at FROM bookmarks WHERE ?1 IS NULL OR project = ?1")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?;
+
+    let now = Utc::now();
+    let mut local_total = 0;
+    let mut ages: HashMap<&str, usize> = HashMap::new();
+    let (mut oldest, mut newest): (Option<String>, Option<String>) = (None, None);
+    for row in rows {
+        let created_at = row.map_err(|e| e.to_string())?;
+        local_total += 1;
+        *ages.entry(age_bucket(&created_at, now)).or_default() += 1;
+        // RFC 3339 时间戳（同为 UTC）可直接按字符串比较
+        if oldest.as_ref().is_none_or(|o| created_at < *o) {
+            oldest = Some(created_at.clone());
+        }
+        if newest.as_ref().is_none_or(|n| created_at > *n) {
+            newest = Some(created_at);
+        }
+    }
+
+    let with_mnemonic = mnemonics.values().sum();
+    let age_distribution = AGE_BUCKETS
+        .iter()
+        .map(|(label, _)| *label)
+        .chain([AGE_OLDER, AGE_UNKNOWN])
+        .map(|label| StatBucket { key: label.to_string(), count: ages.get(label).copied().unwrap_or(0) })
+        .filter(|b| b.key != AGE_UNKNOWN || b.count > 0)
+        .collect();
+
+    Ok(BookmarkStats {
+        total,
+        project_count: projects.len(),
+        by_project: top_buckets(projects, top),
+        by_extension: top_buckets(extensions, top),
+        by_directory: top_buckets(directories, top),
+        by_group: top_buckets(groups, top),
+        by_mnemonic: top_buckets(mnemonics, top),
+        with_mnemonic,
+        with_description,
+        description_coverage: if total == 0 { 0.0 } else { with_description as f64 / total as f64 },
+        local_ages: LocalAgeStats { total: local_total, age_distribution, oldest, newest },
+    })
+}
@@ -0,0 +1,231 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::{AppHandle, Runtime};
+
+use crate::backup_crypto::{decrypt_with_session, is_encrypted};
+use crate::backup_integrity::{parse_backup_content, sha256_hex};
+use crate::backup_store::{load_records, BackupRecord};
+use crate::bookmark_manager::{get_backup_dir, parse_backup_name};
+use crate::db::open_db;
+use crate::project_detect::detect_project_name;
+
+/// 备份中的一个书签，按 (项目, 路径, 行号) 比较
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TrendBookmark {
+    pub project: String,
+    pub file_path: String,
+    pub line_number: i32,
+}
+
+/// 某个备份时刻的书签数量
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct TrendPoint {
+    pub backup_id: String,
+    pub created_at: String,
+    pub count: usize,
+}
+
+/// 一个项目在某个备份来源文件中的书签数量变化
+#[derive(serde::Serialize, Debug)]
+pub struct ProjectSeries {
+    pub project: String,
+    /// 备份的原始文件名，只有同一来源的备份之间才有可比性
+    pub source_file: String,
+    pub points: Vec<TrendPoint>,
+}
+
+/// 相邻两个备份之间某个项目的书签增减
+#[derive(serde::Serialize, Debug)]
+pub struct TrendEvent {
+    pub source_file: String,
+    pub project: String,
+    pub from_backup: String,
+    pub to_backup: String,
+    pub created_at: String,
+    pub added: Vec<TrendBookmark>,
+    pub removed: Vec<TrendBookmark>,
+}
+
+/// 书签趋势
+#[derive(serde::Serialize, Debug)]
+pub struct BookmarkTrends {
+    pub series: Vec<ProjectSeries>,
+    pub events: Vec<TrendEvent>,
+    /// 无法读取或已加密且未解锁的备份
+    pub skipped: Vec<String>,
+}
+
+/// 解析后的一个备份
+struct ParsedSnapshot {
+    id: String,
+    created_at: String,
+    bookmarks: BTreeSet<TrendBookmark>,
+}
+
+fn to_trend_bookmarks(content: &str) -> BTreeSet<TrendBookmark> {
+    parse_backup_content(content)
+        .into_iter()
+        .map(|b| TrendBookmark {
+            // 旧格式备份中没有分组名，按文件路径推断项目
+            project: if b.project_name.is_empty() { detect_project_name(&b.file_path) } else { b.project_name },
+            file_path: b.file_path,
+            line_number: b.line_number,
+        })
+        .collect()
+}
+
+/// 读取并解析备份，未加密的备份使用缓存；加密备份不缓存，避免明文书签写入数据库
+fn load_backup_bookmarks(conn: &Connection, id: &str, bytes: &[u8]) -> Result<Option<BTreeSet<TrendBookmark>>, String> {
+    if is_encrypted(bytes) {
+        return Ok(decrypt_with_session(bytes)
+            .ok()
+            .map(|plain| to_trend_bookmarks(&String::from_utf8_lossy(&plain))));
+    }
+
+    let hash = sha256_hex(bytes);
+    let cached: Option<String> = conn
+        .query_row(
+            "SELECT bookmarks FROM backup_parse_cache WHERE backup_id = ?1 AND sha256 = ?2",
+            params![id, hash],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some(bookmarks) = cached.and_then(|json| serde_json::from_str(&json).ok()) {
+        return Ok(Some(bookmarks));
+    }
+
+    let bookmarks = to_trend_bookmarks(&String::from_utf8_lossy(bytes));
+    let json = serde_json::to_string(&bookmarks).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO backup_parse_cache (backup_id, sha256, bookmarks, parsed_at) VALUES (?1, ?2, ?3, ?4)",
+        params![id, hash, json, Utc::now().to_rfc3339()],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(Some(bookmarks))
+}
+
+fn count_by_project(bookmarks: &BTreeSet<TrendBookmark>) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for b in bookmarks {
+        *counts.entry(b.project.as_str()).or_default() += 1;
+    }
+    counts
+}
+
+/// 解析全部备份，返回每个项目的书签数量时间序列，以及相邻备份之间新增和删除的书签
+/// source_file 可限定备份的原始文件名，project 可限定项目
+#[tauri::command]
+pub fn get_bookmark_trends<R: Runtime>(
+    app: AppHandle<R>,
+    source_file: Option<String>,
+    project: Option<String>,
+) -> Result<BookmarkTrends, String> {
+    let backup_dir = get_backup_dir(&app)?;
+    let conn = open_db(&app)?;
+    let records = load_records(&conn, false)?;
+    let mut trends = BookmarkTrends { series: Vec::new(), events: Vec::new(), skipped: Vec::new() };
+
+    // 按来源文件分组，备份 ID 以时间戳开头，排序即为时间顺序
+    let mut by_source: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    if backup_dir.exists() {
+        for entry in fs::read_dir(&backup_dir).map_err(|e| e.to_string())?.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !entry.path().is_file() {
+                continue;
+            }
+            if let Some((original, _)) = parse_backup_name(&name) {
+                if source_file.as_ref().is_none_or(|s| *s == original) {
+                    by_source.entry(original).or_default().push(name);
+                }
+            }
+        }
+    }
+
+    let mut present = Vec::new();
+    for (source, mut ids) in by_source {
+        ids.sort();
+        let mut snapshots = Vec::new();
+        for id in ids {
+            present.push(id.clone());
+            let bookmarks = match fs::read(backup_dir.join(&id)) {
+                Ok(bytes) => load_backup_bookmarks(&conn, &id, &bytes)?,
+                Err(_) => None,
+            };
+            let Some(mut bookmarks) = bookmarks else {
+                trends.skipped.push(id);
+                continue;
+            };
+            if let Some(project) = &project {
+                bookmarks.retain(|b| b.project == *project);
+            }
+            let created_at = records
+                .get(&id)
+                .map(|r| r.created_at.clone())
+                .unwrap_or_else(|| BackupRecord::from_file_name(&id).created_at);
+            snapshots.push(ParsedSnapshot { id, created_at, bookmarks });
+        }
+
+        // 时间序列：来源中出现过的每个项目在每个备份时刻都有一个点，未出现时为 0
+        let counts: Vec<HashMap<&str, usize>> = snapshots.iter().map(|s| count_by_project(&s.bookmarks)).collect();
+        let projects: BTreeSet<&str> = counts.iter().flat_map(|c| c.keys().copied()).collect();
+        for name in projects {
+            let points = snapshots
+                .iter()
+                .zip(&counts)
+                .map(|(s, c)| TrendPoint {
+                    backup_id: s.id.clone(),
+                    created_at: s.created_at.clone(),
+                    count: c.get(name).copied().unwrap_or(0),
+                })
+                .collect();
+            trends.series.push(ProjectSeries { project: name.to_string(), source_file: source.clone(), points });
+        }
+
+        for pair in snapshots.windows(2) {
+            let (prev, next) = (&pair[0], &pair[1]);
+            let mut changes: BTreeMap<&str, (Vec<TrendBookmark>, Vec<TrendBookmark>)> = BTreeMap::new();
+            for b in next.bookmarks.difference(&prev.bookmarks) {
+                changes.entry(b.project.as_str()).or_default().0.push(b.clone());
+            }
+            for b in prev.bookmarks.difference(&next.bookmarks) {
+                changes.entry(b.project.as_str()).or_default().1.push(b.clone());
+            }
+            for (name, (added, removed)) in changes {
+                trends.events.push(TrendEvent {
+                    source_file: source.clone(),
+                    project: name.to_string(),
+                    from_backup: prev.id.clone(),
+                    to_backup: next.id.clone(),
+                    created_at: next.created_at.clone(),
+                    added,
+                    removed,
+                });
+            }
+        }
+    }
+
+    // 只在未限定来源时清理已删除备份的缓存，避免误删其它来源的缓存
+    if source_file.is_none() {
+        let mut stmt = conn.prepare("SELECT backup_id FROM backup_parse_cache").map_err(|e| e.to_string())?;
+        let cached: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+        for id in cached.iter().filter(|id| !present.contains(id)) {
+            conn.execute("DELETE FROM backup_parse_cache WHERE backup_id = ?1", params![id])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    println!(
+        "Computed bookmark trends: {} series, {} events, {} skipped",
+        trends.series.len(),
+        trends.events.len(),
+        trends.skipped.len()
+    );
+    Ok(trends)
+}
@@ -0,0 +1,189 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Runtime};
+
+use crate::bookmark_manager::{read_bookmarks_from_workspace, ParsedBookmark, PROJECT_DIR_PLACEHOLDER};
+use crate::journal;
+use crate::workspace_writer::{normalize_path, relativize_path, render_group_state, upsert_group_state};
+
+/// VS Code Bookmarks 扩展 (alefragnani.bookmarks) 的 .vscode/bookmarks.json 结构
+#[derive(serde::Serialize, serde::Deserialize, Default, Debug)]
+struct VsCodeBookmarksFile {
+    #[serde(default)]
+    files: Vec<VsCodeFile>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+struct VsCodeFile {
+    /// 相对工作区的路径（旧版本扩展可能保存绝对路径）
+    path: String,
+    #[serde(default)]
+    bookmarks: Vec<VsCodeBookmark>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+struct VsCodeBookmark {
+    /// 0-indexed 行号
+    line: i32,
+    #[serde(default)]
+    column: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+}
+
+fn vscode_bookmarks_path(project_root: &str) -> PathBuf {
+    Path::new(project_root).join(".vscode").join("bookmarks.json")
+}
+
+fn project_dir_name(project_root: &str) -> String {
+    Path::new(project_root)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "未知项目".to_string())
+}
+
+fn load_vscode_file(project_root: &str) -> Result<VsCodeBookmarksFile, String> {
+    let path = vscode_bookmarks_path(project_root);
+    if !path.exists() {
+        return Ok(VsCodeBookmarksFile::default());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+}
+
+/// 将 VS Code 中的路径解析为绝对路径
+fn resolve_vscode_path(path: &str, project_root: &str) -> String {
+    // 扩展可能带有 $ROOTPATH$ 前缀
+    let path = match path.strip_prefix("$ROOTPATH$") {
+        Some(rest) => rest.trim_start_matches(['/', '\\']),
+        None => path,
+    };
+    if Path::new(path).is_absolute() {
+        normalize_path(path)
+    } else {
+        format!("{}/{}", normalize_path(project_root), normalize_path(path))
+    }
+}
+
+/// 将书签路径转换为 VS Code 使用的相对路径，项目外的文件保留绝对路径
+fn to_vscode_path(file_path: &str, project_root: &str) -> String {
+    if let Some(rest) = file_path.strip_prefix(PROJECT_DIR_PLACEHOLDER) {
+        return normalize_path(rest).trim_start_matches('/').to_string();
+    }
+    relativize_path(file_path, project_root).unwrap_or_else(|| normalize_path(file_path))
+}
+
+/// 读取项目中的 VS Code 书签，转换为统一的书签结构
+#[tauri::command]
+pub fn read_vscode_bookmarks(project_root: String) -> Result<Vec<ParsedBookmark>, String> {
+    let project_name = project_dir_name(&project_root);
+    let data = load_vscode_file(&project_root)?;
+
+    let mut bookmarks = Vec::new();
+    for file in data.files {
+        let file_path = resolve_vscode_path(&file.path, &project_root);
+        let file_name = Path::new(&file_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "未知文件".to_string());
+
+        for b in file.bookmarks {
+            bookmarks.push(ParsedBookmark {
+                project_name: project_name.clone(),
+                file_name: file_name.clone(),
+                file_path: file_path.clone(),
+                line_number: b.line + 1,
+                description: b.label.unwrap_or_default(),
+                mnemonic: None,
+                bookmark_type: "匿名书签".to_string(),
+            });
+        }
+    }
+    Ok(bookmarks)
+}
+
+/// 将 VS Code 书签导入到 IDEA workspace XML 的 BookmarksManager 分组中
+/// 分组名默认使用项目目录名，同名分组会被替换；返回导入的书签数量
+#[tauri::command]
+pub fn import_vscode_bookmarks_to_idea<R: Runtime>(
+    app: AppHandle<R>,
+    project_root: String,
+    xml_path: String,
+    group_name: Option<String>,
+) -> Result<usize, String> {
+    let bookmarks = read_vscode_bookmarks(project_root.clone())?;
+    if bookmarks.is_empty() {
+        return Ok(0);
+    }
+
+    let group_name = group_name.unwrap_or_else(|| project_dir_name(&project_root));
+    let content = if Path::new(&xml_path).exists() {
+        fs::read_to_string(&xml_path).map_err(|e| e.to_string())?
+    } else {
+        String::new()
+    };
+
+    let group_xml = render_group_state(&group_name, &bookmarks, Some(&project_root));
+    let updated = upsert_group_state(&content, &group_name, &group_xml);
+    let op = journal::write_file(Path::new(&xml_path), &updated)?;
+    journal::record(&app, &format!("从 VS Code 导入书签到分组 {}", group_name), op);
+
+    println!("Imported {} VS Code bookmarks into {}", bookmarks.len(), xml_path);
+    Ok(bookmarks.len())
+}
+
+/// 将 IDEA 中某个项目的书签导出为 .vscode/bookmarks.json
+/// 与已有的 VS Code 书签合并（相同文件相同行不重复添加）；返回新增的书签数量
+#[tauri::command]
+pub fn export_idea_bookmarks_to_vscode<R: Runtime>(
+    app: AppHandle<R>,
+    workspace_path: String,
+    project_name: String,
+    project_root: String,
+) -> Result<usize, String> {
+    let bookmarks: Vec<ParsedBookmark> = read_bookmarks_from_workspace(workspace_path)?
+        .into_iter()
+        .filter(|b| b.project_name == project_name)
+        .collect();
+
+    let mut data = load_vscode_file(&project_root)?;
+    let mut added = 0;
+
+    for b in &bookmarks {
+        let path = to_vscode_path(&b.file_path, &project_root);
+        let line = b.line_number - 1;
+
+        let resolved = resolve_vscode_path(&path, &project_root);
+        let idx = match data
+            .files
+            .iter()
+            .position(|f| resolve_vscode_path(&f.path, &project_root) == resolved)
+        {
+            Some(idx) => idx,
+            None => {
+                data.files.push(VsCodeFile { path, bookmarks: Vec::new() });
+                data.files.len() - 1
+            }
+        };
+
+        let file = &mut data.files[idx];
+        if file.bookmarks.iter().any(|existing| existing.line == line) {
+            continue;
+        }
+        file.bookmarks.push(VsCodeBookmark {
+            line,
+            column: 0,
+            label: Some(b.description.clone()).filter(|d| !d.is_empty()),
+        });
+        file.bookmarks.sort_by_key(|bm| bm.line);
+        added += 1;
+    }
+
+    let target = vscode_bookmarks_path(&project_root);
+    let json = serde_json::to_string_pretty(&data).map_err(|e| e.to_string())?;
+    let op = journal::write_file(&target, &json)?;
+    journal::record(&app, &format!("导出 {} 的书签到 VS Code", project_name), op);
+
+    println!("Exported {} bookmarks to {:?}", added, target);
+    Ok(added)
+}
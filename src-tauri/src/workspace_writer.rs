@@ -0,0 +1,239 @@
+use crate::bookmark_manager::{
+    extract_attr_value, mnemonic_to_bookmark_type, ParsedBookmark, PROJECT_DIR_PLACEHOLDER,
+};
+
+const LINE_BOOKMARK_PROVIDER: &str = "com.intellij.ide.bookmark.providers.LineBookmarkProvider";
+
+/// 辅助函数：转义 XML 属性值
+pub(crate) fn escape_xml_attr(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// 统一路径分隔符为 `/` 并去掉末尾分隔符
+pub(crate) fn normalize_path(path: &str) -> String {
+    let p = path.replace('\\', "/");
+    if p.len() > 1 { p.trim_end_matches('/').to_string() } else { p }
+}
+
+/// 若 path 位于 root 之下，返回相对路径（使用 `/` 分隔）
+pub(crate) fn relativize_path(path: &str, root: &str) -> Option<String> {
+    let path = normalize_path(path);
+    let root = normalize_path(root);
+    if root.is_empty() {
+        return None;
+    }
+
+    // Windows 路径不区分大小写
+    let prefix = path.get(..root.len())?;
+    let matches = if cfg!(target_os = "windows") {
+        prefix.eq_ignore_ascii_case(&root)
+    } else {
+        prefix == root
+    };
+    if !matches {
+        return None;
+    }
+    path[root.len()..].strip_prefix('/').map(|rest| rest.to_string())
+}
+
+/// 生成 IDEA 书签 URL：位于项目根目录下的文件使用 $PROJECT_DIR$ 相对形式
+pub(crate) fn bookmark_url(file_path: &str, project_root: Option<&str>) -> String {
+    if let Some(rest) = file_path.strip_prefix(PROJECT_DIR_PLACEHOLDER) {
+        return format!("file://$PROJECT_DIR${}", normalize_path(rest));
+    }
+    if let Some(rel) = project_root.and_then(|root| relativize_path(file_path, root)) {
+        return format!("file://$PROJECT_DIR$/{}", rel);
+    }
+    format!("file://{}", normalize_path(file_path))
+}
+
+/// 渲染 IDEA 2025+ 格式的 BookmarkState 列表
+fn render_bookmark_states(bookmarks: &[ParsedBookmark], project_root: Option<&str>) -> String {
+    let mut xml = String::new();
+    for b in bookmarks {
+        xml.push_str("          <BookmarkState>\n            <attributes>\n");
+        xml.push_str(&format!(
+            "              <entry key=\"url\" value=\"{}\" />\n",
+            escape_xml_attr(&bookmark_url(&b.file_path, project_root))
+        ));
+        xml.push_str(&format!(
+            "              <entry key=\"line\" value=\"{}\" />\n",
+            (b.line_number - 1).max(0)
+        ));
+        xml.push_str("            </attributes>\n");
+        xml.push_str(&format!(
+            "            <option name=\"description\" value=\"{}\" />\n",
+            escape_xml_attr(&b.description)
+        ));
+        xml.push_str(&format!(
+            "            <option name=\"provider\" value=\"{}\" />\n",
+            LINE_BOOKMARK_PROVIDER
+        ));
+        if let Some(bookmark_type) = b.mnemonic.as_deref().and_then(mnemonic_to_bookmark_type) {
+            xml.push_str(&format!("            <option name=\"type\" value=\"{}\" />\n", bookmark_type));
+        }
+        xml.push_str("          </BookmarkState>\n");
+    }
+    xml
+}
+
+/// 渲染 IDEA 2025+ 格式的单个 GroupState
+pub(crate) fn render_group_state(group_name: &str, bookmarks: &[ParsedBookmark], project_root: Option<&str>) -> String {
+    format!(
+        "      <GroupState>\n        <option name=\"bookmarks\">\n{}        </option>\n        <option name=\"name\" value=\"{}\" />\n      </GroupState>\n",
+        render_bookmark_states(bookmarks, project_root),
+        escape_xml_attr(group_name)
+    )
+}
+
+/// 在 GroupState 块中读取分组名
+fn group_state_name(block: &str) -> Option<String> {
+    // 分组名位于 GroupState 末尾，取最后一个 name="name" 以跳过书签内部的同名属性
+    let idx = block.rfind("<option name=\"name\"")?;
+    extract_attr_value(&block[idx..], "value")
+}
+
+/// 查找指定名称的 GroupState，返回其在文本中的字节范围
+fn find_group_state(content: &str, group_name: &str) -> Option<(usize, usize)> {
+    let escaped_name = escape_xml_attr(group_name);
+    let mut offset = 0;
+    while let Some(rel) = content[offset..].find("<GroupState>") {
+        let start = offset + rel;
+        let end = start + content[start..].find("</GroupState>")? + "</GroupState>".len();
+        if group_state_name(&content[start..end]).as_deref() == Some(escaped_name.as_str()) {
+            return Some((start, end));
+        }
+        offset = end;
+    }
+    None
+}
+
+/// 向已有的同名 GroupState 追加书签，保留分组的其它选项；分组不存在时返回 None
+pub(crate) fn append_to_group_state(
+    content: &str,
+    group_name: &str,
+    bookmarks: &[ParsedBookmark],
+    project_root: Option<&str>,
+) -> Option<String> {
+    let (start, end) = find_group_state(content, group_name)?;
+    let block = &content[start..end];
+    let states = render_bookmark_states(bookmarks, project_root);
+
+    let bm_idx = start + block.find("<option name=\"bookmarks\"")?;
+    let tag_end = bm_idx + content[bm_idx..].find('>')?;
+    if content[..tag_end].ends_with('/') {
+        // <option name="bookmarks" /> 自闭合形式
+        return Some(format!(
+            "{}<option name=\"bookmarks\">\n{}        </option>{}",
+            &content[..bm_idx],
+            states,
+            &content[tag_end + 1..]
+        ));
+    }
+    Some(format!("{}\n{}{}", &content[..=tag_end], states.trim_end_matches('\n'), &content[tag_end + 1..]))
+}
+
+/// 将 GroupState 写入 workspace XML：同名分组整体替换，否则追加到 BookmarksManager 中
+/// 文件中没有 BookmarksManager 组件时自动创建
+pub(crate) fn upsert_group_state(content: &str, group_name: &str, group_xml: &str) -> String {
+    // 1. 替换同名分组
+    if let Some((start, end)) = find_group_state(content, group_name) {
+        // 保留原有缩进：从行首开始替换
+        let line_start = content[..start].rfind('\n').map(|i| i + 1).unwrap_or(start);
+        let line_end = if content[end..].starts_with('\n') { end + 1 } else { end };
+        return format!("{}{}{}", &content[..line_start], group_xml, &content[line_end..]);
+    }
+
+    // 2. 追加到已有的 groups 选项中
+    if let Some(comp_idx) = content.find("<component name=\"BookmarksManager\"") {
+        let comp = &content[comp_idx..];
+        if let Some(groups_rel) = comp.find("<option name=\"groups\"") {
+            let groups_idx = comp_idx + groups_rel;
+            let tag_end = groups_idx + content[groups_idx..].find('>').unwrap_or(0);
+            if content[..tag_end].ends_with('/') {
+                // <option name="groups" /> 自闭合形式
+                let tag_start = groups_idx;
+                return format!(
+                    "{}<option name=\"groups\">\n{}    </option>{}",
+                    &content[..tag_start],
+                    group_xml,
+                    &content[tag_end + 1..]
+                );
+            }
+            return format!("{}\n{}{}", &content[..=tag_end], group_xml.trim_end_matches('\n'), &content[tag_end + 1..]);
+        }
+        if let Some(open_end_rel) = comp.find('>') {
+            let open_end = comp_idx + open_end_rel;
+            return format!(
+                "{}\n    <option name=\"groups\">\n{}    </option>{}",
+                &content[..=open_end],
+                group_xml,
+                &content[open_end + 1..]
+            );
+        }
+    }
+
+    // 3. 新建 BookmarksManager 组件
+    let component = format!(
+        "  <component name=\"BookmarksManager\">\n    <option name=\"groups\">\n{}    </option>\n  </component>\n",
+        group_xml
+    );
+    match content.rfind("</") {
+        Some(close_idx) if content.trim_start().starts_with('<') => {
+            format!("{}{}{}", &content[..close_idx], component, &content[close_idx..])
+        }
+        _ => format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<project version=\"4\">\n{}</project>\n",
+            component
+        ),
+    }
+}
+
+/// 渲染旧格式 (<bookmark url=... line=... />) 的书签条目
+pub(crate) fn render_legacy_bookmarks(bookmarks: &[ParsedBookmark], project_root: Option<&str>) -> String {
+    let mut xml = String::new();
+    for b in bookmarks {
+        xml.push_str(&format!(
+            "    <bookmark url=\"{}\" line=\"{}\" description=\"{}\"",
+            escape_xml_attr(&bookmark_url(&b.file_path, project_root)),
+            (b.line_number - 1).max(0),
+            escape_xml_attr(&b.description)
+        ));
+        if let Some(m) = &b.mnemonic {
+            xml.push_str(&format!(" mnemonic=\"{}\"", escape_xml_attr(m)));
+        }
+        xml.push_str(" />\n");
+    }
+    xml
+}
+
+/// 将旧格式书签条目追加到 BookmarkManager 组件中，组件不存在时自动创建
+pub(crate) fn append_legacy_bookmarks(content: &str, bookmarks_xml: &str) -> String {
+    if let Some(comp_idx) = content.find("<component name=\"BookmarkManager\"") {
+        let tag_end = comp_idx + content[comp_idx..].find('>').unwrap_or(0);
+        if content[..tag_end].ends_with('/') {
+            // <component name="BookmarkManager" /> 自闭合形式
+            return format!(
+                "{}<component name=\"BookmarkManager\">\n{}  </component>{}",
+                &content[..comp_idx],
+                bookmarks_xml,
+                &content[tag_end + 1..]
+            );
+        }
+        return format!("{}\n{}{}", &content[..=tag_end], bookmarks_xml.trim_end_matches('\n'), &content[tag_end + 1..]);
+    }
+
+    let component = format!("  <component name=\"BookmarkManager\">\n{}  </component>\n", bookmarks_xml);
+    match content.rfind("</") {
+        Some(close_idx) if content.trim_start().starts_with('<') => {
+            format!("{}{}{}", &content[..close_idx], component, &content[close_idx..])
+        }
+        _ => format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<project version=\"4\">\n{}</project>\n",
+            component
+        ),
+    }
+}